@@ -0,0 +1,92 @@
+//! The kinds of things market prices can be fetched for.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+/// Something a provider can return prices for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Asset {
+    /// A stock, fund or other security traded on an exchange, identified by its symbol
+    Security(String),
+    /// The exchange rate from one currency into another, identified by their ISO codes
+    Currency { from: String, to: String },
+    /// A cryptocurrency like `BTC`, priced in the currency `market`
+    Crypto { symbol: String, market: String },
+}
+
+impl fmt::Display for Asset {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Security(symbol) => write!(formatter, "{symbol}"),
+            Self::Currency { from, to } => write!(formatter, "{from}/{to}"),
+            Self::Crypto { symbol, market } => write!(formatter, "{symbol}/{market}"),
+        }
+    }
+}
+
+/// The kind of an [`Asset`], as given in the configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AssetClass {
+    #[default]
+    Security,
+    Fx,
+    Crypto,
+}
+
+impl AssetClass {
+    /// Names accepted by [`AssetClass::from_str`].
+    pub const NAMES: &'static [&'static str] = &["security", "fx", "crypto"];
+
+    /// Returns the asset of this class identified by `symbol`.
+    ///
+    /// Currency pairs are given as `FROM/TO`, e.g. `USD/EUR`, and cryptocurrencies as
+    /// `SYMBOL/MARKET`, e.g. `BTC/EUR`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `symbol` is not a valid symbol for this class.
+    pub fn asset(self, symbol: &str) -> Result<Asset> {
+        match self {
+            Self::Security => Ok(Asset::Security(symbol.to_string())),
+            Self::Fx | Self::Crypto => {
+                let (base, quote) = symbol.split_once('/').ok_or_else(|| Error::UnknownSymbol {
+                    symbol: symbol.to_string(),
+                    message: "pairs have to be given as BASE/QUOTE, e.g. USD/EUR or BTC/EUR"
+                        .to_string(),
+                })?;
+                let (base, quote) = (base.to_string(), quote.to_string());
+                Ok(if self == Self::Fx {
+                    Asset::Currency {
+                        from: base,
+                        to: quote,
+                    }
+                } else {
+                    Asset::Crypto {
+                        symbol: base,
+                        market: quote,
+                    }
+                })
+            }
+        }
+    }
+}
+
+impl FromStr for AssetClass {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name {
+            "security" => Ok(Self::Security),
+            "fx" => Ok(Self::Fx),
+            "crypto" => Ok(Self::Crypto),
+            _ => Err(format!(
+                "unknown asset class `{}`, expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
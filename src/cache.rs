@@ -0,0 +1,163 @@
+//! On-disk cache for provider responses.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Whether and how the cache is consulted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheMode {
+    /// Use cached responses that are younger than the TTL, fetch everything else.
+    #[default]
+    Normal,
+    /// Only use cached responses, regardless of their age, and never access the network.
+    Offline,
+    /// Ignore cached responses, but still store new ones.
+    Refresh,
+}
+
+/// A directory holding one file per cached response.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    directory: PathBuf,
+    ttl: Duration,
+    mode: CacheMode,
+}
+
+impl Cache {
+    /// Creates a cache in `directory` that considers responses older than `ttl` stale.
+    #[must_use]
+    pub const fn new(directory: PathBuf, ttl: Duration, mode: CacheMode) -> Self {
+        Self {
+            directory,
+            ttl,
+            mode,
+        }
+    }
+
+    /// Returns the cache directory of this application inside the user's cache directory,
+    /// e.g. `~/.cache/hledger-get-market-prices`.
+    #[must_use]
+    pub fn default_directory() -> Option<PathBuf> {
+        dirs::cache_dir().map(|directory| directory.join(env!("CARGO_PKG_NAME")))
+    }
+
+    #[must_use]
+    pub const fn mode(&self) -> CacheMode {
+        self.mode
+    }
+
+    #[must_use]
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Turns a list of key components, e.g. provider, function and symbol, into the path
+    /// of the file caching the response.
+    fn path(&self, key: &[&str]) -> PathBuf {
+        let mut path = self.directory.clone();
+        for component in key {
+            let sanitized: String = component
+                .chars()
+                .map(|character| {
+                    if character.is_ascii_alphanumeric() || "-_.".contains(character) {
+                        character
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            path.push(if sanitized.starts_with('.') {
+                format!("_{sanitized}")
+            } else {
+                sanitized
+            });
+        }
+        // Appended instead of using `set_extension`, which would replace the exchange
+        // suffix of symbols like `XDWD.DEX`.
+        path.as_mut_os_string().push(".json");
+        path
+    }
+
+    /// Returns the cached response for `key`, unless it is missing or stale.
+    #[must_use]
+    pub fn get(&self, key: &[&str]) -> Option<String> {
+        let path = self.path(key);
+        match self.mode {
+            CacheMode::Refresh => None,
+            CacheMode::Offline => std::fs::read_to_string(path).ok(),
+            CacheMode::Normal => {
+                if is_fresh(&path, self.ttl) {
+                    std::fs::read_to_string(path).ok()
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Stores `response` for `key`.
+    ///
+    /// The cache is only an optimization, so failing to write it is not an error.
+    pub fn put(&self, key: &[&str], response: &str) {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(path, response);
+    }
+}
+
+fn is_fresh(path: &Path, ttl: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < ttl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(directory: &Path, mode: CacheMode) -> Cache {
+        Cache::new(directory.to_path_buf(), Duration::from_hours(1), mode)
+    }
+
+    #[test]
+    fn exchange_suffixes_are_kept() {
+        let path = cache(Path::new("/cache"), CacheMode::Normal).path(&[
+            "alpha-vantage",
+            "TIME_SERIES_DAILY",
+            "XDWD.DEX",
+        ]);
+        assert_eq!(
+            path,
+            Path::new("/cache/alpha-vantage/TIME_SERIES_DAILY/XDWD.DEX.json")
+        );
+    }
+
+    #[test]
+    fn keys_cant_leave_the_cache_directory() {
+        let path =
+            cache(Path::new("/cache"), CacheMode::Normal).path(&["..", "../etc/passwd", ".hidden"]);
+        assert_eq!(path, Path::new("/cache/_../_.._etc_passwd/_.hidden.json"));
+        assert!(path
+            .components()
+            .all(|component| component != std::path::Component::ParentDir));
+    }
+
+    #[test]
+    fn refreshing_ignores_cached_responses() {
+        let directory = std::env::temp_dir().join(format!(
+            "{}-cache-test-{}",
+            env!("CARGO_PKG_NAME"),
+            std::process::id()
+        ));
+        let key = ["alpha-vantage", "SYMBOL_SEARCH", "XDWD"];
+        let normal = cache(&directory, CacheMode::Normal);
+        normal.put(&key, "{}");
+        assert_eq!(normal.get(&key).as_deref(), Some("{}"));
+        assert_eq!(cache(&directory, CacheMode::Refresh).get(&key), None);
+        let _ = std::fs::remove_dir_all(directory);
+    }
+}
@@ -0,0 +1,328 @@
+//! The configuration file listing all tracked commodities.
+//!
+//! ```toml
+//! output = "prices.journal"
+//! preferred-regions = ["XETRA", "Frankfurt"]
+//!
+//! [[commodity]]
+//! symbol = "XDWD.DEX"
+//! commodity = "MSCIWRLD"
+//! currency = "€"
+//! decimal-digits = 3
+//! price-field = "adjusted-close"
+//! frequency = "monthly"
+//!
+//! [[commodity]]
+//! asset-class = "fx"
+//! symbol = "USD/EUR"
+//! commodity = "$"
+//! currency = "€"
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::output::{AmountStyle, DigitGrouping, OutputFormat, PriceTime};
+use crate::period::DateRange;
+use crate::provider::ProviderKind;
+use crate::resample::{Aggregation, Frequency};
+use crate::scale::Scale;
+use crate::{AssetClass, Error, HistoryOptions, PriceField, Result};
+
+/// Contents of the configuration file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// File that prices are appended to, unless a commodity specifies its own
+    pub output: Option<PathBuf>,
+    /// Regions whose listings are shown first by a symbol search, the most preferred one
+    /// first
+    #[serde(default)]
+    pub preferred_regions: Vec<String>,
+    #[serde(default, rename = "commodity")]
+    pub commodities: Vec<CommodityConfig>,
+}
+
+/// A `[[commodity]]` entry that is added to a configuration file with
+/// [`Config::append_commodity`].
+#[derive(Debug, Clone, Default)]
+pub struct NewCommodity {
+    /// Symbol the provider knows the commodity by
+    pub symbol: String,
+    /// Commodity name used in the journal
+    pub commodity: String,
+    /// Commodity name of the currency the prices are denoted in
+    pub currency: String,
+    /// Whether the listing is quoted in a minor currency unit and needs `scale = "auto"`
+    pub auto_scale: bool,
+    /// How the prices are written. The rounding mode is not written.
+    pub style: AmountStyle,
+}
+
+impl NewCommodity {
+    /// Returns the entry as TOML, starting with the `[[commodity]]` header.
+    #[must_use]
+    pub fn to_toml(&self) -> String {
+        let string = |text: &str| toml::Value::from(text).to_string();
+        let mut lines = vec![
+            "[[commodity]]".to_string(),
+            format!("symbol = {}", string(&self.symbol)),
+            format!("commodity = {}", string(&self.commodity)),
+            format!("currency = {}", string(&self.currency)),
+        ];
+        if self.auto_scale {
+            lines.push("scale = \"auto\"".to_string());
+        }
+        let style = &self.style;
+        if let Some(digits) = style.decimal_digits {
+            lines.push(format!("decimal-digits = {digits}"));
+        }
+        if let Some(separator) = style.decimal_separator {
+            lines.push(format!(
+                "decimal-separator = {}",
+                string(&separator.to_string())
+            ));
+        }
+        if let Some(separator) = style.digit_group_separator {
+            lines.push(format!(
+                "digit-group-separator = {}",
+                string(&separator.to_string())
+            ));
+        }
+        if style.digit_grouping == Some(DigitGrouping::Indian) {
+            lines.push("digit-grouping = \"indian\"".to_string());
+        }
+        if let Some(before) = style.currency_before {
+            lines.push(format!("currency-before = {before}"));
+        }
+        if let Some(spaced) = style.currency_spaced {
+            lines.push(format!("currency-spaced = {spaced}"));
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+/// A commodity whose market prices are tracked.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CommodityConfig {
+    #[serde(default)]
+    pub asset_class: AssetClass,
+    /// Symbol the provider knows the commodity by, or `FROM/TO` for exchange rates and
+    /// `SYMBOL/MARKET` for cryptocurrencies
+    pub symbol: String,
+    /// Commodity name used in the journal
+    pub commodity: String,
+    /// Commodity name of the currency the prices are denoted in
+    pub currency: String,
+    #[serde(default)]
+    pub provider: ProviderKind,
+    #[serde(default)]
+    pub price_field: PriceField,
+    #[serde(default)]
+    pub frequency: Frequency,
+    #[serde(default)]
+    pub aggregation: Aggregation,
+    /// ISO code of a currency to convert the prices into, see
+    /// [`crate::HistoryOptions::convert_to`]
+    pub convert_to: Option<String>,
+    #[serde(default)]
+    pub scale: Scale,
+    #[serde(default)]
+    pub format: OutputFormat,
+    /// Character separating year, month and day, see [`crate::output::DateStyle`]
+    pub date_separator: Option<char>,
+    /// Time of day written into Ledger price directives
+    pub time: Option<PriceTime>,
+    #[serde(flatten)]
+    pub style: AmountStyle,
+    /// File that prices are appended to, overriding [`Config::output`]
+    pub output: Option<PathBuf>,
+    /// Keys that are not settings of the entry. [`Config::load`] takes `separator`, like
+    /// the `--separator` option, as the decimal separator and rejects all others.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, toml::Value>,
+}
+
+impl Config {
+    /// Returns the location of the configuration file in the user's configuration
+    /// directory, e.g. `~/.config/hledger-get-market-prices/config.toml`.
+    #[must_use]
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir()
+            .map(|directory| directory.join(env!("CARGO_PKG_NAME")).join("config.toml"))
+    }
+
+    /// Reads the configuration file at `path`.
+    ///
+    /// Relative output paths are resolved against the directory containing the file.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read or is not a valid configuration.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut config: Self = toml::from_str(&contents).map_err(|error| Error::Config {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        })?;
+
+        for commodity in &mut config.commodities {
+            commodity
+                .take_extra_keys()
+                .map_err(|message| Error::Config {
+                    path: path.to_path_buf(),
+                    message,
+                })?;
+        }
+
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let outputs = config.output.iter_mut().chain(
+            config
+                .commodities
+                .iter_mut()
+                .filter_map(|commodity| commodity.output.as_mut()),
+        );
+        for output in outputs {
+            *output = directory.join(&*output);
+        }
+
+        Ok(config)
+    }
+
+    /// Appends `commodity` to the configuration file at `path`, creating the file if it
+    /// doesn't exist yet.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the existing file is not a valid configuration, already tracks the symbol or
+    /// can't be written.
+    pub fn append_commodity(path: &Path, commodity: &NewCommodity) -> Result<()> {
+        let io_error = |source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let mut text = commodity.to_toml();
+        if path.exists() {
+            let config = Self::load(path)?;
+            if let Some(existing) = config
+                .commodities
+                .iter()
+                .find(|existing| existing.symbol.eq_ignore_ascii_case(&commodity.symbol))
+            {
+                return Err(Error::Unsupported(format!(
+                    "{} is already tracked as {} in {}",
+                    commodity.symbol,
+                    existing.commodity,
+                    path.display()
+                )));
+            }
+            let contents = std::fs::read_to_string(path).map_err(io_error)?;
+            if !contents.is_empty() {
+                text.insert_str(
+                    0,
+                    if contents.ends_with('\n') {
+                        "\n"
+                    } else {
+                        "\n\n"
+                    },
+                );
+            }
+        } else if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(io_error)?;
+        }
+
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| std::io::Write::write_all(&mut file, text.as_bytes()))
+            .map_err(io_error)
+    }
+}
+
+impl CommodityConfig {
+    /// Returns the file prices of this commodity are appended to, if any.
+    #[must_use]
+    pub fn output<'a>(&'a self, config: &'a Config) -> Option<&'a Path> {
+        self.output.as_deref().or(config.output.as_deref())
+    }
+
+    /// Moves `separator` from the extra keys into the style.
+    ///
+    /// Fails if it contradicts `decimal-separator` or any other extra key is left.
+    fn take_extra_keys(&mut self) -> std::result::Result<(), String> {
+        if let Some(value) = self.extra.remove("separator") {
+            let mut characters = value.as_str().unwrap_or_default().chars();
+            let (Some(separator), None) = (characters.next(), characters.next()) else {
+                return Err(format!(
+                    "invalid separator {value} of {}, expected a single character",
+                    self.symbol
+                ));
+            };
+            match self.style.decimal_separator {
+                Some(decimal_separator) if decimal_separator != separator => {
+                    return Err(format!(
+                        "the separator and decimal-separator of {} differ",
+                        self.symbol
+                    ))
+                }
+                _ => self.style.decimal_separator = Some(separator),
+            }
+        }
+        self.extra.keys().next().map_or(Ok(()), |key| {
+            Err(format!(
+                "unknown key `{key}` in the entry of {}",
+                self.symbol
+            ))
+        })
+    }
+
+    /// Returns the options to fetch the prices of this commodity within `range` with.
+    #[must_use]
+    pub fn history_options(&self, range: DateRange) -> HistoryOptions {
+        HistoryOptions {
+            range,
+            price_field: self.price_field,
+            frequency: self.frequency,
+            aggregation: self.aggregation,
+            convert_to: self.convert_to.clone(),
+            scale: self.scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commodity(extra_lines: &str) -> std::result::Result<CommodityConfig, String> {
+        let text =
+            format!("symbol = \"XDWD.DEX\"\ncommodity = \"MSCI\"\ncurrency = \"€\"\n{extra_lines}");
+        let mut commodity: CommodityConfig = toml::from_str(&text).unwrap();
+        commodity.take_extra_keys().map(|()| commodity)
+    }
+
+    #[test]
+    fn separator_sets_the_decimal_separator() {
+        let parsed = commodity("separator = \",\"\ndecimal-digits = 2").unwrap();
+        assert_eq!(parsed.style.decimal_separator, Some(','));
+        assert_eq!(parsed.style.decimal_digits, Some(2));
+        assert!(parsed.extra.is_empty());
+
+        assert!(commodity("separator = \",\"\ndecimal-separator = \".\"").is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert_eq!(
+            commodity("decimal-digit = 2").unwrap_err(),
+            "unknown key `decimal-digit` in the entry of XDWD.DEX"
+        );
+    }
+}
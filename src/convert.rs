@@ -0,0 +1,143 @@
+//! Converting prices into another currency using exchange rates of the same day.
+
+use chrono::{Days, NaiveDate};
+use rust_decimal::Decimal;
+
+use crate::provider::{HistoryQuery, Interval, PriceProvider, Quote};
+use crate::{Asset, Error, Result};
+
+/// How many days before the first price exchange rates are fetched, so that a price on a
+/// day without an exchange rate, e.g. a holiday, can use an earlier one.
+const RATE_LOOKBACK_DAYS: u64 = 14;
+
+/// Returns the ISO code of the currency prices of `asset` are denoted in.
+///
+/// For securities, this is the currency the provider reports for the listing.
+pub async fn quote_currency(provider: &dyn PriceProvider, asset: &Asset) -> Result<String> {
+    match asset {
+        Asset::Security(symbol) => Some(crate::find_listing(provider, symbol).await?.currency)
+            .filter(|currency| !currency.is_empty())
+            .ok_or_else(|| Error::UnknownSymbol {
+                symbol: symbol.clone(),
+                message: "could not find out which currency it is traded in".to_string(),
+            }),
+        Asset::Currency { to, .. } => Ok(to.clone()),
+        Asset::Crypto { market, .. } => Ok(market.clone()),
+    }
+}
+
+/// Converts the prices of `quotes` from the currency `from` into the currency `to`, using
+/// the close rate of the same day or, if there is none, the last one before it.
+///
+/// Quotes older than all known exchange rates are dropped.
+pub async fn convert(
+    provider: &dyn PriceProvider,
+    quotes: Vec<Quote>,
+    from: &str,
+    to: &str,
+) -> Result<Vec<Quote>> {
+    let Some(oldest) = quotes.iter().map(|quote| quote.date).min() else {
+        return Ok(quotes);
+    };
+    let query = HistoryQuery {
+        since: oldest.checked_sub_days(Days::new(RATE_LOOKBACK_DAYS)),
+        adjusted: false,
+        interval: Interval::Daily,
+    };
+    let pair = Asset::Currency {
+        from: from.to_string(),
+        to: to.to_string(),
+    };
+    let mut rates: Vec<(NaiveDate, Decimal)> = provider
+        .history(&pair, &query)
+        .await?
+        .into_iter()
+        .map(|rate| (rate.date, rate.close))
+        .collect();
+    rates.sort_by_key(|&(date, _)| date);
+
+    Ok(quotes
+        .into_iter()
+        .filter_map(|quote| {
+            let known = rates.partition_point(|&(date, _)| date <= quote.date);
+            let &(_, rate) = rates.get(known.checked_sub(1)?)?;
+            Some(quote.map_prices(|price| price * rate))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::SymbolMatch;
+
+    /// Provider knowing only the exchange rates it was created with.
+    struct Rates(Vec<(&'static str, i64)>);
+
+    #[async_trait::async_trait]
+    impl PriceProvider for Rates {
+        async fn search(&self, _query: &str) -> Result<Vec<SymbolMatch>> {
+            Ok(Vec::new())
+        }
+
+        async fn history(&self, _asset: &Asset, _query: &HistoryQuery) -> Result<Vec<Quote>> {
+            Ok(self.0.iter().map(|&(day, rate)| quote(day, rate)).collect())
+        }
+
+        fn listing_symbol(&self, _ticker: &str, _exchange_code: &str) -> Option<String> {
+            None
+        }
+    }
+
+    fn quote(day: &str, close: i64) -> Quote {
+        let close = Decimal::from(close);
+        Quote {
+            date: day.parse().unwrap(),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            adjusted_close: None,
+            volume: None,
+        }
+    }
+
+    fn closes(quotes: &[Quote]) -> Vec<(String, Decimal)> {
+        quotes
+            .iter()
+            .map(|quote| (quote.date.to_string(), quote.close))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn the_last_rate_is_carried_over_gaps() {
+        let provider = Rates(vec![("2022-01-03", 3), ("2021-12-31", 2)]);
+        let quotes = vec![
+            quote("2022-01-04", 10),
+            quote("2022-01-03", 10),
+            quote("2022-01-01", 10),
+            quote("2021-12-31", 10),
+        ];
+        let converted = convert(&provider, quotes, "USD", "EUR").await.unwrap();
+        assert_eq!(
+            closes(&converted),
+            [
+                ("2022-01-04".to_string(), Decimal::from(30)),
+                ("2022-01-03".to_string(), Decimal::from(30)),
+                ("2022-01-01".to_string(), Decimal::from(20)),
+                ("2021-12-31".to_string(), Decimal::from(20)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn quotes_older_than_all_rates_are_dropped() {
+        let provider = Rates(vec![("2022-01-03", 3)]);
+        let quotes = vec![quote("2022-01-03", 10), quote("2021-12-31", 10)];
+        let converted = convert(&provider, quotes, "USD", "EUR").await.unwrap();
+        assert_eq!(
+            closes(&converted),
+            [("2022-01-03".to_string(), Decimal::from(30))]
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Errors returned by this crate.
+
+/// Everything that can go wrong while fetching market prices.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The environment variable holding the API key is not set.
+    #[error("environment variable {variable} is not set")]
+    MissingApiKey { variable: &'static str },
+    /// The API key is not usable or was rejected by the provider.
+    #[error("invalid API key: {0}")]
+    InvalidApiKey(String),
+    /// The configuration file is invalid.
+    #[error("invalid configuration file {}: {message}", path.display())]
+    Config {
+        path: std::path::PathBuf,
+        message: String,
+    },
+    /// The requested style of amounts can't be written unambiguously.
+    #[error("invalid amount style: {0}")]
+    InvalidStyle(String),
+    /// A commodity name can't be used in the requested output format.
+    #[error("invalid commodity name `{name}`: {message}")]
+    InvalidCommodity { name: String, message: String },
+    /// The provider can't deliver what was requested, e.g. exchange rates.
+    #[error("unsupported: {0}")]
+    Unsupported(String),
+    /// The provider does not know the requested symbol.
+    #[error("unknown symbol `{symbol}`: {message}")]
+    UnknownSymbol { symbol: String, message: String },
+    /// The provider refused the request because too many requests were made.
+    #[error("rate limit exceeded: {0}")]
+    RateLimited(String),
+    /// The provider could not be reached.
+    #[error("network failure: {0}")]
+    Network(String),
+    /// Offline mode was requested, but the response is not cached.
+    #[error("not cached: {0}")]
+    NotCached(String),
+    /// The provider answered with something that could not be understood.
+    #[error("malformed response: {0}")]
+    MalformedResponse(String),
+    /// A file could not be read or written.
+    #[error("could not access {}: {source}", path.display())]
+    Io {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+    /// A bug in this application.
+    #[error("internal error: {0}")]
+    Internal(String),
+}
+
+impl Error {
+    /// Returns the process exit code used for this error.
+    ///
+    /// The values follow the BSD `sysexits.h` conventions, so that e.g. a rate limit
+    /// (`EX_TEMPFAIL`) can be told apart from a configuration problem (`EX_CONFIG`).
+    #[must_use]
+    pub const fn exit_code(&self) -> u8 {
+        match self {
+            Self::Unsupported(_) | Self::InvalidStyle(_) | Self::InvalidCommodity { .. } => 64,
+            Self::UnknownSymbol { .. } => 65,
+            Self::NotCached(_) => 66,
+            Self::Network(_) => 69,
+            Self::Internal(_) => 70,
+            Self::Io { .. } => 74,
+            Self::RateLimited(_) => 75,
+            Self::MalformedResponse(_) => 76,
+            Self::InvalidApiKey(_) => 77,
+            Self::MissingApiKey { .. } | Self::Config { .. } => 78,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
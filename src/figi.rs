@@ -0,0 +1,163 @@
+//! Looking up the exchange listings of an [`Identifier`] with an `OpenFIGI` compatible
+//! mapping API, see <https://www.openfigi.com/api>.
+
+use serde::Deserialize;
+
+use crate::cache::CacheMode;
+use crate::identifier::Identifier;
+use crate::provider::ProviderSettings;
+use crate::{Error, Result};
+
+/// Base URL of the public `OpenFIGI` API.
+pub const DEFAULT_URL: &str = "https://api.openfigi.com";
+
+const API_KEY_VARIABLE: &str = "OPENFIGI_API_KEY";
+
+/// A listing of a security on an exchange, as returned by the mapping API.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FigiListing {
+    /// Financial Instrument Global Identifier of the listing
+    pub figi: String,
+    pub name: Option<String>,
+    /// Ticker symbol, e.g. `XDWD`
+    pub ticker: Option<String>,
+    /// Bloomberg code of the exchange, e.g. `GY` for XETRA
+    #[serde(rename = "exchCode")]
+    pub exchange_code: Option<String>,
+    /// Kind of security, e.g. `ETP`
+    pub security_type: Option<String>,
+}
+
+/// The answer to a single mapping job.
+#[derive(Debug, Deserialize)]
+struct MappingResult {
+    data: Option<Vec<FigiListing>>,
+    warning: Option<String>,
+    error: Option<String>,
+}
+
+/// Client of an `OpenFIGI` compatible mapping API.
+pub struct OpenFigi {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    settings: ProviderSettings,
+}
+
+impl OpenFigi {
+    /// Creates a client sending requests to `base_url`, using the API key in
+    /// `OPENFIGI_API_KEY` if it is set. The API can be used without a key, but with a lower
+    /// rate limit.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the HTTP client can't be created.
+    pub fn from_env(base_url: &str, settings: ProviderSettings) -> Result<Self> {
+        Ok(Self {
+            client: crate::build_http_client()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: std::env::var(API_KEY_VARIABLE).ok(),
+            settings,
+        })
+    }
+
+    /// Returns all listings of the security identified by `identifier`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the API doesn't know the identifier or can't be reached.
+    pub async fn listings(&self, identifier: &Identifier) -> Result<Vec<FigiListing>> {
+        let id_type = match identifier {
+            Identifier::Isin(_) => "ID_ISIN",
+            Identifier::Wkn(_) => "ID_WERTPAPIER",
+            Identifier::Cusip(_) => "ID_CUSIP",
+        };
+        let key = ["openfigi", id_type, identifier.value()];
+
+        let cached = self
+            .settings
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(&key));
+        let fetched = cached.is_none();
+        let response = if let Some(response) = cached {
+            response
+        } else {
+            if let Some(cache) = self
+                .settings
+                .cache
+                .as_ref()
+                .filter(|cache| cache.mode() == CacheMode::Offline)
+            {
+                return Err(Error::NotCached(format!(
+                    "no cached OpenFIGI response found in {}",
+                    cache.directory().display()
+                )));
+            }
+            self.request(id_type, identifier.value()).await?
+        };
+
+        let result = serde_json::from_str::<Vec<MappingResult>>(&response)
+            .map_err(|error| Error::MalformedResponse(error.to_string()))?
+            .pop()
+            .ok_or_else(|| {
+                Error::MalformedResponse("the mapping API returned no result".to_string())
+            })?;
+        match result {
+            MappingResult {
+                data: Some(listings),
+                ..
+            } => {
+                if let Some(cache) = self.settings.cache.as_ref().filter(|_| fetched) {
+                    cache.put(&key, &response);
+                }
+                Ok(listings)
+            }
+            MappingResult {
+                warning: Some(message),
+                ..
+            }
+            | MappingResult {
+                error: Some(message),
+                ..
+            } => Err(Error::UnknownSymbol {
+                symbol: identifier.value().to_string(),
+                message,
+            }),
+            MappingResult { .. } => Err(Error::MalformedResponse(
+                "the mapping API returned neither listings nor an error".to_string(),
+            )),
+        }
+    }
+
+    async fn request(&self, id_type: &str, value: &str) -> Result<String> {
+        let mut request = self
+            .client
+            .post(format!("{}/v3/mapping", self.base_url))
+            .json(&serde_json::json!([{ "idType": id_type, "idValue": value }]));
+        if let Some(api_key) = &self.api_key {
+            request = request.header("X-OPENFIGI-APIKEY", api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|error| Error::Network(error.to_string()))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|error| Error::Network(error.to_string()))?;
+        match status {
+            reqwest::StatusCode::TOO_MANY_REQUESTS => Err(Error::RateLimited(body)),
+            reqwest::StatusCode::UNAUTHORIZED => Err(Error::InvalidApiKey(format!(
+                "the mapping API rejected the key in {API_KEY_VARIABLE}: {body}"
+            ))),
+            status if status.is_success() => Ok(body),
+            status => Err(Error::MalformedResponse(format!(
+                "the mapping API answered with {status}: {body}"
+            ))),
+        }
+    }
+}
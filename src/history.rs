@@ -0,0 +1,223 @@
+//! Fetching the prices of a configured commodity and writing them out, either as a whole
+//! or appended to an existing journal without repeating prices already in it.
+
+use std::io::Write;
+use std::path::Path;
+
+use chrono::NaiveTime;
+
+use crate::config::CommodityConfig;
+use crate::output::{self, DateStyle, JournalFormat, OutputFormat, PriceTime};
+use crate::period::DateRange;
+use crate::provider::{PriceProvider, ProviderSettings};
+use crate::{journal, Asset, Error, MarketPrice, Result};
+
+/// Fetches the prices of `commodity` within `range` and renders them in the configured
+/// output format, newest first.
+///
+/// Price directives are preceded by a comment naming this application and CSV by a header
+/// line.
+///
+/// # Errors
+///
+/// Fails if the style or commodity names can't be written in the format or fetching the
+/// prices fails.
+pub async fn render(
+    settings: &ProviderSettings,
+    commodity: &CommodityConfig,
+    range: DateRange,
+) -> Result<String> {
+    let format = match commodity.format {
+        OutputFormat::Journal(format) => format,
+        OutputFormat::Csv | OutputFormat::Jsonl => {
+            return render_series(settings, commodity, range).await
+        }
+    };
+    let prices = fetch_prices(settings, commodity, format, range).await?;
+    let mut rendered = output::generated_by_comment();
+    rendered.push('\n');
+    rendered.push_str(&render_directives(
+        &prices.prices,
+        format,
+        commodity,
+        &prices.date_style,
+    ));
+    Ok(rendered)
+}
+
+/// Appends the prices of `commodity` within `range` to the journal at `path` in
+/// chronological order, creating it if it doesn't exist yet.
+///
+/// Only prices of periods after the one of the latest price of the commodity already in
+/// the journal are fetched, so running this repeatedly never writes a price twice.
+///
+/// # Errors
+///
+/// Fails if the configured format is not a journal format, the journal can't be read or
+/// written or fetching the prices fails.
+pub async fn append(
+    settings: &ProviderSettings,
+    commodity: &CommodityConfig,
+    range: DateRange,
+    path: &Path,
+) -> Result<()> {
+    let OutputFormat::Journal(format) = commodity.format else {
+        return Err(Error::Unsupported(
+            "only journal formats can be appended to an existing file".to_string(),
+        ));
+    };
+    let existing_journal = read_existing(path)?;
+    let range = journal::latest_price_date(&existing_journal, &commodity.commodity)
+        .map_or(range, |latest| {
+            range.after(commodity.frequency.last_day(latest))
+        });
+
+    let prices = fetch_prices(settings, commodity, format, range).await?;
+    if prices.prices.is_empty() {
+        return Ok(());
+    }
+    let mut chronological_prices = prices.prices;
+    chronological_prices.reverse();
+    let directives =
+        render_directives(&chronological_prices, format, commodity, &prices.date_style);
+    // The comment is only written once, when the file is started.
+    let text = if existing_journal.is_empty() {
+        format!("{}\n{directives}", output::generated_by_comment())
+    } else if existing_journal.ends_with('\n') {
+        directives
+    } else {
+        format!("\n{directives}")
+    };
+
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// Prices fetched for price directives, together with how their dates are written.
+struct FetchedPrices {
+    prices: Vec<MarketPrice>,
+    date_style: DateStyle,
+}
+
+async fn fetch_prices(
+    settings: &ProviderSettings,
+    commodity: &CommodityConfig,
+    format: JournalFormat,
+    range: DateRange,
+) -> Result<FetchedPrices> {
+    commodity.style.validate()?;
+    DateStyle {
+        separator: commodity.date_separator,
+        time: None,
+    }
+    .validate()?;
+    format.validate_commodity(&commodity.commodity)?;
+    format.validate_commodity(&commodity.currency)?;
+
+    let asset = commodity.asset_class.asset(&commodity.symbol)?;
+    let provider = commodity.provider.create(settings)?;
+    let prices = crate::get_history(
+        provider.as_ref(),
+        &asset,
+        &commodity.commodity,
+        &commodity.currency,
+        &commodity.history_options(range),
+    )
+    .await?;
+
+    let time = match commodity.time {
+        _ if format != JournalFormat::Ledger => None,
+        Some(PriceTime::At(time)) => Some(time),
+        Some(PriceTime::MarketClose) => Some(market_close(provider.as_ref(), &asset).await?),
+        None => None,
+    };
+    Ok(FetchedPrices {
+        prices,
+        date_style: DateStyle {
+            separator: commodity.date_separator,
+            time,
+        },
+    })
+}
+
+/// Renders `prices` as price directives in the order given.
+fn render_directives(
+    prices: &[MarketPrice],
+    format: JournalFormat,
+    commodity: &CommodityConfig,
+    date_style: &DateStyle,
+) -> String {
+    let mut rendered = String::new();
+    for price in prices {
+        rendered.push_str(&format.price_directive(price, &commodity.style, date_style));
+        rendered.push('\n');
+    }
+    rendered
+}
+
+/// Fetches all prices of each day of `commodity` within `range` and renders them as CSV
+/// or JSON Lines, newest first.
+async fn render_series(
+    settings: &ProviderSettings,
+    commodity: &CommodityConfig,
+    range: DateRange,
+) -> Result<String> {
+    let asset = commodity.asset_class.asset(&commodity.symbol)?;
+    let provider = commodity.provider.create(settings)?;
+    let quotes =
+        crate::get_quotes(provider.as_ref(), &asset, &commodity.history_options(range)).await?;
+
+    let mut rendered = String::new();
+    let record = if commodity.format == OutputFormat::Csv {
+        rendered.push_str(output::CSV_HEADER);
+        rendered.push('\n');
+        output::csv_record
+    } else {
+        output::json_line
+    };
+    for quote in &quotes {
+        rendered.push_str(&record(
+            &commodity.symbol,
+            &commodity.commodity,
+            &commodity.currency,
+            quote,
+        ));
+        rendered.push('\n');
+    }
+    Ok(rendered)
+}
+
+/// Returns the time the exchange listing `asset` closes, in the exchange's time zone.
+async fn market_close(provider: &dyn PriceProvider, asset: &Asset) -> Result<NaiveTime> {
+    let Asset::Security(symbol) = asset else {
+        return Err(Error::Unsupported(format!(
+            "{asset} is not traded on an exchange with a closing time"
+        )));
+    };
+    let listing = crate::find_listing(provider, symbol).await?;
+    NaiveTime::parse_from_str(&listing.market_close, "%H:%M").map_err(|error| {
+        Error::MalformedResponse(format!(
+            "invalid market close time `{}`: {error}",
+            listing.market_close
+        ))
+    })
+}
+
+/// Reads the journal at `path`, treating a file that doesn't exist yet as empty.
+fn read_existing(path: &Path) -> Result<String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(contents),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(source) => Err(Error::Io {
+            path: path.to_path_buf(),
+            source,
+        }),
+    }
+}
@@ -0,0 +1,177 @@
+//! Securities identification numbers like ISINs, which are independent of exchanges.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A number identifying a security on all exchanges it is listed on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Identifier {
+    /// International Securities Identification Number, e.g. `IE00BJ0KDQ92`
+    Isin(String),
+    /// German Wertpapierkennnummer, e.g. `A1XB5U`
+    Wkn(String),
+    /// Committee on Uniform Securities Identification Procedures number, e.g. `037833100`
+    Cusip(String),
+}
+
+impl Identifier {
+    /// Returns the identifier itself, in upper case.
+    #[must_use]
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Isin(value) | Self::Wkn(value) | Self::Cusip(value) => value,
+        }
+    }
+
+    /// Returns the name of the kind of identifier, e.g. `ISIN`.
+    #[must_use]
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Isin(_) => "ISIN",
+            Self::Wkn(_) => "WKN",
+            Self::Cusip(_) => "CUSIP",
+        }
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{} {}", self.kind(), self.value())
+    }
+}
+
+/// Parses an ISIN, CUSIP or WKN, telling them apart by their length and validating the
+/// check digit of ISINs and CUSIPs.
+impl FromStr for Identifier {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let value = text.trim().to_ascii_uppercase();
+        if !value
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric())
+        {
+            return Err(format!(
+                "invalid identifier `{text}`, expected only letters and digits"
+            ));
+        }
+
+        let (identifier, check_digit) = match value.len() {
+            12 if value[..2].chars().all(|character| character.is_ascii_alphabetic()) => {
+                (Self::Isin(value.clone()), isin_check_digit(&value[..11]))
+            }
+            12 => {
+                return Err(format!(
+                    "invalid ISIN `{text}`, expected it to start with a country code"
+                ))
+            }
+            9 => (Self::Cusip(value.clone()), cusip_check_digit(&value[..8])),
+            6 => return Ok(Self::Wkn(value)),
+            _ => {
+                return Err(format!(
+                    "invalid identifier `{text}`, expected an ISIN (12 characters), a CUSIP (9 characters) or a WKN (6 characters)"
+                ))
+            }
+        };
+        let last = value.chars().last().and_then(|last| last.to_digit(10));
+        if last == Some(check_digit) {
+            Ok(identifier)
+        } else {
+            Err(format!(
+                "invalid {} `{text}`, the check digit should be {check_digit}",
+                identifier.kind()
+            ))
+        }
+    }
+}
+
+/// Returns the value of a character in ISINs and CUSIPs: digits stand for themselves and
+/// letters count on from 10, i.e. `A` is 10 and `Z` is 35.
+fn character_value(character: char) -> u32 {
+    character.to_digit(36).unwrap_or(0)
+}
+
+/// Computes the check digit of an ISIN from its first 11 characters, using the Luhn
+/// algorithm on the digits the characters stand for.
+fn isin_check_digit(payload: &str) -> u32 {
+    let digits: Vec<u32> = payload
+        .chars()
+        .map(character_value)
+        .flat_map(|value| {
+            if value < 10 {
+                vec![value]
+            } else {
+                vec![value / 10, value % 10]
+            }
+        })
+        .collect();
+    // Doubling starts with the rightmost digit, as the check digit is appended after it.
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(position, &digit)| {
+            if position % 2 == 0 {
+                let doubled = digit * 2;
+                doubled / 10 + doubled % 10
+            } else {
+                digit
+            }
+        })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+/// Computes the check digit of a CUSIP from its first 8 characters.
+fn cusip_check_digit(payload: &str) -> u32 {
+    let sum: u32 = payload
+        .chars()
+        .map(character_value)
+        .enumerate()
+        .map(|(position, value)| {
+            let value = if position % 2 == 1 { value * 2 } else { value };
+            value / 10 + value % 10
+        })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_isins_are_accepted() {
+        assert_eq!(
+            "IE00BJ0KDQ92".parse(),
+            Ok(Identifier::Isin("IE00BJ0KDQ92".to_string()))
+        );
+        assert_eq!(
+            "us0378331005".parse(),
+            Ok(Identifier::Isin("US0378331005".to_string()))
+        );
+    }
+
+    #[test]
+    fn valid_cusips_are_accepted() {
+        assert_eq!(
+            "037833100".parse(),
+            Ok(Identifier::Cusip("037833100".to_string()))
+        );
+    }
+
+    #[test]
+    fn wrong_check_digits_are_rejected() {
+        assert_eq!(
+            "IE00BJ0KDQ93".parse::<Identifier>(),
+            Err("invalid ISIN `IE00BJ0KDQ93`, the check digit should be 2".to_string())
+        );
+        assert!("037833101".parse::<Identifier>().is_err());
+    }
+
+    #[test]
+    fn wkns_are_told_apart_by_their_length() {
+        assert_eq!("A1XB5U".parse(), Ok(Identifier::Wkn("A1XB5U".to_string())));
+        assert!("XDWD".parse::<Identifier>().is_err());
+    }
+}
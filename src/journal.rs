@@ -0,0 +1,418 @@
+//! Reading of existing hledger journals.
+
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+use crate::output::{AmountStyle, DigitGrouping};
+use crate::{Error, Result};
+
+/// Parses a date in any of the formats hledger accepts for full dates
+/// (`2022-01-07`, `2022/01/07`, `2022.01.07`, also without leading zeros).
+#[must_use]
+pub fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&date.replace(['/', '.'], "-"), "%Y-%m-%d").ok()
+}
+
+/// Splits a commodity name off the start of `text`, returning it without quotes
+/// together with the remaining text.
+fn split_commodity(text: &str) -> Option<(&str, &str)> {
+    if let Some(quoted) = text.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        Some((&quoted[..end], &quoted[end + 1..]))
+    } else {
+        let end = text.find(char::is_whitespace).unwrap_or(text.len());
+        Some((&text[..end], &text[end..]))
+    }
+}
+
+/// Parses a `P` directive or a beancount `price` directive, returning its date and the
+/// (unquoted) name of the commodity it gives a price for.
+#[must_use]
+pub fn parse_price_directive(line: &str) -> Option<(NaiveDate, &str)> {
+    if let Some(directive) = parse_beancount_price_directive(line) {
+        return Some(directive);
+    }
+    let rest = line.strip_prefix('P')?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let date_end = rest.find(char::is_whitespace)?;
+    let date = parse_date(&rest[..date_end])?;
+    let mut rest = rest[date_end..].trim_start();
+
+    // Ledger style directives may contain a time after the date.
+    if let Some(time_end) = rest.find(char::is_whitespace) {
+        if rest[..time_end].contains(':') {
+            rest = rest[time_end..].trim_start();
+        }
+    }
+
+    let (commodity, _) = split_commodity(rest)?;
+    if commodity.is_empty() {
+        return None;
+    }
+    Some((date, commodity))
+}
+
+/// Parses a beancount `price` directive like `2022-01-07 price MSCIWRLD 84.838 EUR`.
+fn parse_beancount_price_directive(line: &str) -> Option<(NaiveDate, &str)> {
+    let mut words = line.split_whitespace();
+    let date = NaiveDate::parse_from_str(words.next()?, "%Y-%m-%d").ok()?;
+    if words.next()? != "price" {
+        return None;
+    }
+    Some((date, words.next()?))
+}
+
+/// Returns the date of the newest `P` directive for `commodity` in `journal`.
+#[must_use]
+pub fn latest_price_date(journal: &str, commodity: &str) -> Option<NaiveDate> {
+    let commodity = commodity.trim_matches('"');
+    journal
+        .lines()
+        .filter_map(parse_price_directive)
+        .filter(|(_, directive_commodity)| *directive_commodity == commodity)
+        .map(|(date, _)| date)
+        .max()
+}
+
+/// An amount as written in a journal, split into its parts.
+struct WrittenAmount<'a> {
+    commodity: &'a str,
+    /// The number without sign, e.g. `1.000,5`
+    number: &'a str,
+    commodity_before: bool,
+    spaced: bool,
+}
+
+/// Whether `character` can be part of a number in a journal.
+const fn is_number_character(character: char) -> bool {
+    character.is_ascii_digit() || character == '.' || character == ','
+}
+
+/// Returns the length of the number at the start of `text`. Digit groups may also be
+/// separated by single spaces, as in `1 000,00`.
+fn number_length(text: &str) -> usize {
+    let mut length = 0;
+    let mut characters = text.char_indices().peekable();
+    while let Some((index, character)) = characters.next() {
+        let next_is_digit = characters
+            .peek()
+            .is_some_and(|(_, next)| next.is_ascii_digit());
+        if !(is_number_character(character) || character == ' ' && next_is_digit) {
+            break;
+        }
+        length = index + character.len_utf8();
+    }
+    length
+}
+
+/// Splits off the commodity name written before an amount, which ends at the first digit,
+/// sign or whitespace unless it is quoted.
+fn split_commodity_before(text: &str) -> Option<(&str, &str)> {
+    if text.starts_with('"') {
+        return split_commodity(text);
+    }
+    let end = text
+        .find(|character: char| {
+            character.is_ascii_digit() || character.is_whitespace() || "-+".contains(character)
+        })
+        .unwrap_or(text.len());
+    Some((&text[..end], &text[end..]))
+}
+
+/// Parses an amount like `-1.000,50 €`, `$12.3` or `3 "MSCI WRLD"`.
+fn parse_amount(text: &str) -> Option<WrittenAmount<'_>> {
+    let text = text.trim();
+    let unsigned = text.trim_start_matches(['-', '+']);
+    if unsigned.starts_with(is_number_character) {
+        let number_end = number_length(unsigned);
+        let rest = &unsigned[number_end..];
+        let (commodity, _) = split_commodity(rest.trim_start())?;
+        Some(WrittenAmount {
+            commodity,
+            number: &unsigned[..number_end],
+            commodity_before: false,
+            spaced: rest.starts_with(char::is_whitespace),
+        })
+    } else {
+        let (commodity, rest) = split_commodity_before(unsigned)?;
+        let spaced = rest.starts_with(char::is_whitespace);
+        let rest = rest.trim_start().trim_start_matches(['-', '+']);
+        let number_end = number_length(rest);
+        Some(WrittenAmount {
+            commodity,
+            number: &rest[..number_end],
+            commodity_before: true,
+            spaced,
+        })
+    }
+    .filter(|amount| {
+        !amount.commodity.is_empty() && amount.number.starts_with(|c: char| c.is_ascii_digit())
+    })
+}
+
+/// Derives the style of `amount`. Like hledger, a single `.` or `,` is taken as decimal
+/// separator, and one that occurs several times or before another one as digit group
+/// separator. Spaces can only separate digit groups.
+fn amount_style(amount: &WrittenAmount) -> AmountStyle {
+    let marks: Vec<char> = amount
+        .number
+        .chars()
+        .filter(|character| !character.is_ascii_digit())
+        .collect();
+    let (decimal_separator, digit_group_separator) = match marks[..] {
+        [] => (None, None),
+        [' ', ..] => (marks.iter().copied().find(|&mark| mark != ' '), Some(' ')),
+        [mark] => (Some(mark), None),
+        [first, .., last] => {
+            if first == last {
+                (None, Some(last))
+            } else {
+                (Some(last), Some(first))
+            }
+        }
+    };
+    // Only amounts with at least three digit groups tell how digits are grouped.
+    let digit_grouping = digit_group_separator.and_then(|separator| {
+        let integer = decimal_separator
+            .and_then(|decimal_separator| amount.number.split(decimal_separator).next())
+            .unwrap_or(amount.number);
+        let groups: Vec<&str> = integer.split(separator).collect();
+        match groups[..] {
+            [_, ref middle @ .., _] if !middle.is_empty() => {
+                Some(if middle.iter().all(|group| group.len() == 2) {
+                    DigitGrouping::Indian
+                } else {
+                    DigitGrouping::Thousands
+                })
+            }
+            _ => None,
+        }
+    });
+    let decimal_digits = decimal_separator.map_or(0, |separator| {
+        amount
+            .number
+            .rsplit_once(separator)
+            .map_or(0, |(_, fraction)| fraction.len())
+    });
+
+    AmountStyle {
+        decimal_separator,
+        digit_group_separator,
+        digit_grouping,
+        decimal_digits: Some(decimal_digits),
+        currency_before: Some(amount.commodity_before),
+        currency_spaced: Some(amount.spaced),
+        ..AmountStyle::default()
+    }
+}
+
+/// Returns the amounts written in `line`, which may be a posting or a `P` directive.
+fn amounts_in_line(line: &str) -> Vec<WrittenAmount<'_>> {
+    let line = line.split(';').next().unwrap_or_default();
+    let amounts = if line.starts_with(char::is_whitespace) {
+        // The account name of a posting ends at two spaces or a tab.
+        let line = line.trim_start();
+        match [line.find("  "), line.find('\t')]
+            .into_iter()
+            .flatten()
+            .min()
+        {
+            Some(end) => &line[end..],
+            None => return Vec::new(),
+        }
+    } else if let Some((_, commodity)) = parse_price_directive(line) {
+        let start = line
+            .find(commodity)
+            .map_or(line.len(), |start| start + commodity.len());
+        line[start..].trim_start_matches('"')
+    } else {
+        return Vec::new();
+    };
+
+    amounts.split(['@', '=']).filter_map(parse_amount).collect()
+}
+
+/// Parses a `commodity` directive, returning the style it declares for `commodity`.
+///
+/// Both the single line form (`commodity 1.000,00 €`) and the form with a `format`
+/// subdirective are understood.
+fn declared_style(lines: &[&str], index: usize, commodity: &str) -> Option<AmountStyle> {
+    let declaration = lines[index]
+        .strip_prefix("commodity")?
+        .split(';')
+        .next()?
+        .trim();
+    if let Some(amount) = parse_amount(declaration) {
+        return (amount.commodity == commodity).then(|| amount_style(&amount));
+    }
+    if split_commodity(declaration)?.0 != commodity {
+        return None;
+    }
+    lines[index + 1..]
+        .iter()
+        .take_while(|line| line.starts_with(char::is_whitespace))
+        .filter_map(|line| line.trim().strip_prefix("format"))
+        .filter_map(parse_amount)
+        .find(|amount| amount.commodity == commodity)
+        .map(|amount| amount_style(&amount))
+}
+
+/// Infers how amounts of `commodity` are written in `journal`.
+///
+/// A `commodity` directive is used if there is one. Otherwise, like hledger does, the
+/// first amount of the commodity determines the separators and the position of the
+/// commodity, and the amount with the most decimal digits determines the precision.
+#[must_use]
+pub fn commodity_style(journal: &str, commodity: &str) -> Option<AmountStyle> {
+    let commodity = commodity.trim_matches('"');
+    let lines: Vec<&str> = journal.lines().collect();
+    if let Some(style) = (0..lines.len()).find_map(|index| declared_style(&lines, index, commodity))
+    {
+        return Some(style);
+    }
+
+    let styles: Vec<AmountStyle> = lines
+        .iter()
+        .flat_map(|line| amounts_in_line(line))
+        .filter(|amount| amount.commodity == commodity)
+        .map(|amount| amount_style(&amount))
+        .collect();
+    let first = styles.first()?;
+    let decimal_separator = styles.iter().find_map(|style| style.decimal_separator);
+    let digit_group_separator = styles.iter().find_map(|style| style.digit_group_separator);
+    let digit_grouping = styles.iter().find_map(|style| style.digit_grouping);
+    Some(AmountStyle {
+        decimal_separator,
+        digit_group_separator,
+        digit_grouping,
+        decimal_digits: styles.iter().filter_map(|style| style.decimal_digits).max(),
+        ..first.clone()
+    })
+}
+
+/// Reads the journal at `path` together with all journal files it includes.
+///
+/// Included files that can't be read, are given as glob patterns or are in another format,
+/// e.g. `include timeclock:work.timeclock`, are skipped.
+///
+/// # Errors
+///
+/// Fails if the file at `path` can't be read.
+pub fn read(path: &Path) -> Result<String> {
+    let mut visited = Vec::new();
+    let mut journal = String::new();
+    read_into(path, &mut visited, &mut journal)?;
+    Ok(journal)
+}
+
+/// Resolves the argument of an `include` directive in a file in `directory`, returning
+/// `None` if it isn't a single journal file.
+fn include_path(directory: &Path, included: &str) -> Option<PathBuf> {
+    // A format prefix like `journal:` is at least two letters long, so that Windows drive
+    // letters aren't mistaken for one.
+    let included = match included.split_once(':') {
+        Some((format, path))
+            if format.len() > 1
+                && format
+                    .chars()
+                    .all(|character| character.is_ascii_alphabetic()) =>
+        {
+            (format == "journal").then_some(path)?
+        }
+        _ => included,
+    };
+    // Glob patterns are not supported.
+    if included.is_empty() || included.contains(['*', '?', '[']) {
+        return None;
+    }
+    match included.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            Some(dirs::home_dir()?.join(rest.trim_start_matches(['/', '\\'])))
+        }
+        _ => Some(directory.join(included)),
+    }
+}
+
+fn read_into(path: &Path, visited: &mut Vec<PathBuf>, journal: &mut String) -> Result<()> {
+    if visited.iter().any(|visited_path| visited_path == path) {
+        return Ok(());
+    }
+    visited.push(path.to_path_buf());
+
+    let contents = std::fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    for line in contents.lines() {
+        if let Some(included) = line.strip_prefix("include ").map(str::trim) {
+            // Only the style of amounts is taken from the journal, so it is better to miss
+            // some of them than to fail.
+            if let Some(included) = include_path(directory, included) {
+                let _ = read_into(&included, visited, journal);
+            }
+        } else {
+            journal.push_str(line);
+            journal.push('\n');
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commodity_directive_declares_the_style() {
+        let journal = "commodity 1.000,000 €\n\n2022-01-01 x\n    a  5 €\n    b\n";
+        let style = commodity_style(journal, "€").unwrap();
+        assert_eq!(style.decimal_separator, Some(','));
+        assert_eq!(style.digit_group_separator, Some('.'));
+        assert_eq!(style.decimal_digits, Some(3));
+        assert_eq!(style.currency_before, Some(false));
+        assert_eq!(style.currency_spaced, Some(true));
+    }
+
+    #[test]
+    fn style_is_inferred_from_amounts_with_the_symbol_before() {
+        let journal = "2022-01-01 x\n    a  $1,234.5\n    b\n";
+        let style = commodity_style(journal, "$").unwrap();
+        assert_eq!(style.decimal_separator, Some('.'));
+        assert_eq!(style.digit_group_separator, Some(','));
+        assert_eq!(style.decimal_digits, Some(1));
+        assert_eq!(style.currency_before, Some(true));
+        assert_eq!(style.currency_spaced, Some(false));
+    }
+
+    #[test]
+    fn spaces_can_separate_digit_groups() {
+        let journal = "2022-01-01 x\n    a  1 000,25 CHF\n    b\n";
+        let style = commodity_style(journal, "CHF").unwrap();
+        assert_eq!(style.decimal_separator, Some(','));
+        assert_eq!(style.digit_group_separator, Some(' '));
+        assert_eq!(style.decimal_digits, Some(2));
+        assert_eq!(style.currency_before, Some(false));
+    }
+
+    #[test]
+    fn indian_grouping_is_recognized() {
+        let journal = "2022-01-01 x\n    a  ₹12,34,567.89\n    b\n";
+        let style = commodity_style(journal, "₹").unwrap();
+        assert_eq!(style.digit_group_separator, Some(','));
+        assert_eq!(style.digit_grouping, Some(DigitGrouping::Indian));
+        assert_eq!(style.decimal_digits, Some(2));
+    }
+
+    #[test]
+    fn unknown_commodities_have_no_style() {
+        assert_eq!(
+            commodity_style("2022-01-01 x\n    a  5 €\n    b\n", "$"),
+            None
+        );
+    }
+}
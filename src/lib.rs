@@ -1,17 +1,43 @@
 #![deny(clippy::pedantic)]
 #![deny(clippy::cargo)]
 #![deny(clippy::nursery)]
+// Duplicate versions are pulled in by our dependencies and can't be fixed here.
+#![allow(clippy::multiple_crate_versions)]
 
-fn get_alpha_vantage_client() -> alpha_vantage::api::ApiClient {
-    let api_key = &std::env::var("HLEDGER_GET_MARKET_PRICES_API_KEY").unwrap_or_else(|error| {
-        match error {
-            std::env::VarError::NotPresent => eprintln!("Environment variable HLEDGER_GET_MARKET_PRICES_API_KEY is not set.\nPlease set this variable to your Alpha Vantage API key and try again."),
-            std::env::VarError::NotUnicode(_) => eprintln!("Environment variable HLEDGER_GET_MARKET_PRICES_API_KEY is not set.\nPlease recheck whether this variable is indeed set to your API key.")
-        }
+mod asset;
+pub mod cache;
+pub mod config;
+mod convert;
+mod error;
+pub mod figi;
+pub mod history;
+pub mod identifier;
+pub mod journal;
+pub mod output;
+pub mod period;
+mod price;
+pub mod provider;
+pub mod rate_limit;
+pub mod resample;
+pub mod scale;
+pub mod search;
+
+pub use asset::{Asset, AssetClass};
+pub use error::{Error, Result};
+pub use price::{MarketPrice, PriceField};
+pub use provider::{Quote, SymbolMatch};
+pub use rust_decimal::Decimal;
+
+use std::collections::hash_map::{Entry, HashMap};
 
-        std::process::exit(1);
-    });
+use figi::OpenFigi;
+use identifier::Identifier;
+use period::DateRange;
+use provider::{HistoryQuery, Interval, PriceProvider};
+use resample::{Aggregation, Frequency};
+use scale::Scale;
 
+pub(crate) fn build_http_client() -> Result<reqwest::Client> {
     let user_agent_for_http_requests = concat!(
         env!("CARGO_PKG_NAME"),
         " V",
@@ -21,101 +47,261 @@ fn get_alpha_vantage_client() -> alpha_vantage::api::ApiClient {
         ")"
     );
 
-    let reqwest_client = reqwest::Client::builder()
+    reqwest::Client::builder()
         .user_agent(user_agent_for_http_requests)
         .build()
-        .unwrap_or_else(|error| report_application_bug("Could not build reqwest client", error));
-
-    alpha_vantage::set_api(api_key, reqwest_client)
+        .map_err(|error| Error::Internal(format!("could not build reqwest client: {error:?}")))
 }
 
-fn report_application_bug<E: std::error::Error>(error_string: &str, error: E) -> ! {
-    eprintln!("An unexpected problem occured that the application can't recover from.\n\nDetails about the error are below. If you believe the invocation of hledger-get-market-prices is correct, I'd appreciate a bug report at {}/issues/new.\n\nError message: {}\nError: {:?}", env!("CARGO_PKG_REPOSITORY"), error_string, error);
+/// Options for [`get_history`] and [`get_history_for_stock`].
+#[derive(Debug, Clone, Default)]
+pub struct HistoryOptions {
+    /// Only prices for days within this range are returned
+    pub range: DateRange,
+    /// Which of the prices of a day is used
+    pub price_field: PriceField,
+    /// How many prices are returned
+    pub frequency: Frequency,
+    /// How the prices of a period are combined if `frequency` is not daily
+    pub aggregation: Aggregation,
+    /// ISO code of a currency to convert the prices into, using the exchange rate of
+    /// each day
+    pub convert_to: Option<String>,
+    /// How the prices are scaled, e.g. for listings quoted in pence
+    pub scale: Scale,
+}
 
-    std::process::exit(1);
+/// Searches `provider` for listings matching `search_query`, best matches first.
+///
+/// # Errors
+///
+/// Fails if the search request fails.
+pub async fn search_stock_symbol(
+    provider: &dyn PriceProvider,
+    search_query: &str,
+) -> Result<Vec<SymbolMatch>> {
+    let mut matches = provider.search(search_query).await?;
+    matches.sort_by(|a, b| b.match_score.total_cmp(&a.match_score));
+    Ok(matches)
 }
 
-pub async fn search_stock_symbol(search_query: String) {
-    let search = get_alpha_vantage_client()
-        .search(&search_query)
-        .json()
-        .await
-        .unwrap_or_else(|error| {
-            report_application_bug("alpha_vantage returned error during `search`: {:?}", error)
-        });
-    let results = search.result();
-    println!("{:>20} | {:>9} – {:20}", "Region", "Symbol", "Name");
-    println!("");
-    for result in results {
-        println!(
-            "{:>20} | {:>9} – {:20}",
-            result.region(),
-            result.symbol(),
-            result.name()
+/// Returns the listings of the security identified by `identifier` that `provider` has
+/// prices for, best matches first.
+///
+/// The listings are looked up with `figi` and completed by searching `provider` for each
+/// ticker symbol. Listings the search doesn't return only have a symbol, name and type.
+///
+/// # Errors
+///
+/// Fails if `figi` doesn't know the identifier or a request fails.
+pub async fn resolve_identifier(
+    figi: &OpenFigi,
+    provider: &dyn PriceProvider,
+    identifier: &Identifier,
+) -> Result<Vec<SymbolMatch>> {
+    let mut listings: Vec<SymbolMatch> = Vec::new();
+    let mut searches: HashMap<String, Vec<SymbolMatch>> = HashMap::new();
+    for listing in figi.listings(identifier).await? {
+        let (Some(ticker), Some(exchange_code)) = (&listing.ticker, &listing.exchange_code) else {
+            continue;
+        };
+        let Some(symbol) = provider.listing_symbol(ticker, exchange_code) else {
+            continue;
+        };
+        if listings
+            .iter()
+            .any(|known| known.symbol.eq_ignore_ascii_case(&symbol))
+        {
+            continue;
+        }
+
+        let found = match searches.entry(ticker.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(provider.search(ticker).await?),
+        };
+        listings.push(
+            found
+                .iter()
+                .find(|found| found.symbol.eq_ignore_ascii_case(&symbol))
+                .cloned()
+                .unwrap_or_else(|| SymbolMatch {
+                    symbol,
+                    name: listing.name.clone().unwrap_or_default(),
+                    asset_type: listing.security_type.clone().unwrap_or_default(),
+                    region: String::new(),
+                    currency: String::new(),
+                    market_open: String::new(),
+                    market_close: String::new(),
+                    timezone: String::new(),
+                    match_score: 0.0,
+                }),
         );
     }
+    listings.sort_by(|a, b| b.match_score.total_cmp(&a.match_score));
+    Ok(listings)
+}
+
+/// Looks up the listing of `stock_symbol` with a search request.
+///
+/// # Errors
+///
+/// Fails if the search request fails or doesn't find a listing with exactly this symbol.
+pub async fn find_listing(provider: &dyn PriceProvider, stock_symbol: &str) -> Result<SymbolMatch> {
+    provider
+        .search(stock_symbol)
+        .await?
+        .into_iter()
+        .find(|listing| listing.symbol.eq_ignore_ascii_case(stock_symbol))
+        .ok_or_else(|| Error::UnknownSymbol {
+            symbol: stock_symbol.to_string(),
+            message: "searching for it didn't find a listing with exactly this symbol".to_string(),
+        })
 }
 
+/// Returns the market prices of `stock_symbol`, newest first.
+///
+/// Each price is denoted as one unit of `stock_commodity_name` costing an amount of
+/// `currency_commodity_name`.
+///
+/// # Errors
+///
+/// See [`get_history`].
 pub async fn get_history_for_stock(
-    stock_symbol: String,
-    stock_commodity_name: String,
-    currency_commodity_name: String,
-    separator: char,
-    decimal_digits: Option<usize>,
-    currency_symbol_before: bool,
-) {
-    let stock_name = stock_commodity_name;
-    let search = get_alpha_vantage_client()
-        .stock_time(
-            alpha_vantage::stock_time::StockFunction::Daily,
-            &stock_symbol,
-        )
-        .output_size(alpha_vantage::api::OutputSize::Full)
-        .json()
-        .await
-        .unwrap_or_else(|error| {
-            report_application_bug("alpha_vantage returned error during `stock_time`", error)
-        });
-
-    let mut entries = search.entry().clone();
-
-    entries.sort_by(|a, b| a.time().cmp(b.time()).reverse());
-
-    let mut last_datetime: Option<String> = None;
-    for entry in entries.iter() {
-        let current_datetime = entry.time();
-        if let Some(last_datetime) = last_datetime {
-            assert!(*last_datetime > *current_datetime);
-        }
-        last_datetime = Some(current_datetime.to_string());
+    provider: &dyn PriceProvider,
+    stock_symbol: &str,
+    stock_commodity_name: &str,
+    currency_commodity_name: &str,
+    options: &HistoryOptions,
+) -> Result<Vec<MarketPrice>> {
+    get_history(
+        provider,
+        &Asset::Security(stock_symbol.to_string()),
+        stock_commodity_name,
+        currency_commodity_name,
+        options,
+    )
+    .await
+}
+
+/// Returns the market prices of `asset`, newest first.
+///
+/// Each price is denoted as one unit of `commodity_name` costing an amount of
+/// `currency_commodity_name`.
+///
+/// # Errors
+///
+/// See [`get_quotes`]. Also fails if the provider doesn't supply the requested price field.
+pub async fn get_history(
+    provider: &dyn PriceProvider,
+    asset: &Asset,
+    commodity_name: &str,
+    currency_commodity_name: &str,
+    options: &HistoryOptions,
+) -> Result<Vec<MarketPrice>> {
+    get_quotes(provider, asset, options)
+        .await?
+        .into_iter()
+        .map(|quote| {
+            Ok(MarketPrice {
+                date: quote.date,
+                commodity: commodity_name.to_string(),
+                amount: options.price_field.select(&quote).ok_or_else(|| {
+                    Error::MalformedResponse(format!(
+                        "the provider didn't return the requested price for {}",
+                        quote.date
+                    ))
+                })?,
+                currency: currency_commodity_name.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Returns all prices of `asset` the provider knows for each day, newest first.
+///
+/// The quotes are scaled, converted and resampled as requested by `options`. With a
+/// frequency other than daily, each quote is the one of the last trading day of its period
+/// or, when averaging, the average of all quotes in the period. Periods that haven't
+/// ended yet, either before the end of the range or today, are left out.
+///
+/// # Errors
+///
+/// Fails if the request fails or the provider returns two quotes for the same day. When
+/// converting prices, it also fails if the currency of `asset` can't be found out.
+pub async fn get_quotes(
+    provider: &dyn PriceProvider,
+    asset: &Asset,
+    options: &HistoryOptions,
+) -> Result<Vec<Quote>> {
+    // Weekly and monthly series contain the close price of the last trading day, but e.g.
+    // the highest price of the whole period, so they can only be used for close prices.
+    let interval = if options.aggregation == Aggregation::Last
+        && matches!(
+            options.price_field,
+            PriceField::Close | PriceField::AdjustedClose
+        ) {
+        options.frequency.provider_interval()
+    } else {
+        Interval::Daily
+    };
+    let query = HistoryQuery {
+        since: options.range.begin,
+        adjusted: options.price_field.needs_adjusted_prices(),
+        interval,
+    };
+
+    let mut quotes = provider.history(asset, &query).await?;
+    quotes.retain(|quote| options.range.contains(quote.date));
+
+    quotes.sort_by(|a, b| a.date.cmp(&b.date).reverse());
+
+    if let Some(pair) = quotes.windows(2).find(|pair| pair[0].date == pair[1].date) {
+        return Err(Error::MalformedResponse(format!(
+            "got more than one price for {}",
+            pair[0].date
+        )));
     }
 
-    println!(
-        "; Generated by {}",
-        concat!(env!("CARGO_PKG_NAME"), " V", env!("CARGO_PKG_VERSION"))
-    );
-    for entry in entries.iter() {
-        let current_datetime = entry.time();
-        let price = entry.close();
-        let mut price_string: String = decimal_digits
-            .map_or(format!("{}", price), |decimal_digits| {
-                format!("{:.*}", decimal_digits, price)
-            });
-
-        if separator != '.' {
-            price_string = price_string.replace('.', &separator.to_string());
+    let factor = match options.scale {
+        Scale::Factor(factor) => factor,
+        Scale::Auto => Decimal::ONE,
+    };
+    // Exchange rates only exist for major currency units, so prices quoted in a minor unit
+    // always have to be scaled before converting them. An explicit factor would be applied
+    // on top of that, which is never what was meant.
+    let (source, subunits) = if options.scale == Scale::Auto || options.convert_to.is_some() {
+        let currency = convert::quote_currency(provider, asset).await?;
+        match scale::minor_unit(&currency) {
+            Some((major, _)) if factor != Decimal::ONE => {
+                return Err(Error::Unsupported(format!(
+                    "{asset} is quoted in {currency}, which is always converted into {major} before converting into another currency, so it can't be scaled by a factor as well; use `--scale auto` instead"
+                )))
+            }
+            Some((major, subunits)) => (Some(major.to_string()), subunits),
+            None => (Some(currency), Decimal::ONE),
         }
-
-        if currency_symbol_before {
-            println!(
-                "P {} {} {}{}",
-                current_datetime, stock_name, currency_commodity_name, price_string
-            );
-        } else {
-            println!(
-                "P {} {} {} {}",
-                current_datetime, stock_name, price_string, currency_commodity_name
-            );
+    } else {
+        (None, Decimal::ONE)
+    };
+    if factor != Decimal::ONE || subunits != Decimal::ONE {
+        quotes = quotes
+            .into_iter()
+            .map(|quote| quote.map_prices(|price| price * factor / subunits))
+            .collect();
+    }
+    if let (Some(source), Some(target)) = (source, &options.convert_to) {
+        if !source.eq_ignore_ascii_case(target) {
+            quotes = convert::convert(provider, quotes, &source, target).await?;
         }
     }
+
+    // The period containing today isn't over yet, so its price isn't final.
+    let today = chrono::Local::now().date_naive();
+    let until = options.range.end.map_or(today, |end| end.min(today));
+    Ok(resample::resample(
+        quotes,
+        options.frequency,
+        options.aggregation,
+        until,
+    ))
 }
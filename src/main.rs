@@ -1,14 +1,85 @@
 #![deny(clippy::pedantic)]
 #![deny(clippy::cargo)]
 #![deny(clippy::nursery)]
+// Duplicate versions are pulled in by our dependencies and can't be fixed here.
+#![allow(clippy::multiple_crate_versions)]
 
-use clap::{Parser, Subcommand};
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+use hledger_get_market_prices::cache::{Cache, CacheMode};
+use hledger_get_market_prices::config::{CommodityConfig, Config, NewCommodity};
+use hledger_get_market_prices::figi::{self, OpenFigi};
+use hledger_get_market_prices::history;
+use hledger_get_market_prices::identifier::Identifier;
+use hledger_get_market_prices::output::{
+    AmountStyle, DigitGrouping, OutputFormat, PriceTime, RoundingMode, SearchFormat,
+};
+use hledger_get_market_prices::period::{self, DateRange};
+use hledger_get_market_prices::provider::{ProviderKind, ProviderSettings};
+use hledger_get_market_prices::rate_limit::RateLimiter;
+use hledger_get_market_prices::resample::{Aggregation, Frequency};
+use hledger_get_market_prices::scale::{self, Scale};
+use hledger_get_market_prices::search::SearchFilter;
+use hledger_get_market_prices::{journal, AssetClass, Error, PriceField, Result, SymbolMatch};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
 struct App {
     #[clap(subcommand)]
     command: Command,
+    #[clap(
+        long,
+        global = true,
+        parse(from_os_str),
+        help = "Configuration file to use instead of the one in the user's configuration directory"
+    )]
+    config: Option<PathBuf>,
+    #[clap(
+        long,
+        global = true,
+        parse(from_os_str),
+        help = "Journal to take the style of amounts from, i.e. decimal and digit group separators, number of decimal digits and currency placement [default: $LEDGER_FILE, if it can be read]"
+    )]
+    journal: Option<PathBuf>,
+    #[clap(
+        long,
+        global = true,
+        default_value = "5",
+        help = "How many requests to send to the provider per minute at most, 0 for no limit"
+    )]
+    requests_per_minute: u32,
+    #[clap(
+        long,
+        global = true,
+        default_value = "3",
+        help = "How often to retry a request that failed because of a rate limit or network problem"
+    )]
+    max_retries: u32,
+    #[clap(
+        long,
+        global = true,
+        default_value = "720",
+        help = "For how many minutes cached responses are used before fetching them again"
+    )]
+    cache_ttl: u64,
+    #[clap(
+        long,
+        global = true,
+        conflicts_with = "refresh",
+        help = "Only use cached responses and never access the network"
+    )]
+    offline: bool,
+    #[clap(
+        long,
+        global = true,
+        help = "Ignore cached responses and fetch everything again"
+    )]
+    refresh: bool,
 }
 
 #[derive(Subcommand, Debug)]
@@ -16,62 +87,799 @@ enum Command {
     #[clap(
         about = "Search for a stock symbol. \nUse this if you don't know the exact stock symbol of the stock you are interested in."
     )]
-    SearchStockSymbol { search_query: String },
-    #[clap(about = "Outputs historic market prices of a stock in a hledger compatible format.")]
-    History {
-        #[clap(help = "Symbol of the stock as given by the `history` subcommand")]
-        stock_symbol: String,
-        #[clap(help = "Commodity name to use for the stock")]
-        stock_commodity_name: String,
-        #[clap(help = "Commodity name to use for the currency the market prices is denoted in")]
-        currency_commodity_name: String,
+    SearchStockSymbol {
+        search_query: String,
         #[clap(
-            short,
             long,
-            help = "Number of digits after the decimal point to return."
+            default_value = "alpha-vantage",
+            possible_values = ProviderKind::NAMES,
+            help = "Which service to search"
         )]
-        decimal_digits: Option<usize>,
+        provider: ProviderKind,
+        #[clap(flatten)]
+        options: SearchArgs,
+    },
+    #[clap(
+        about = "Find the stock symbols of a security by its ISIN, WKN or CUSIP.\nThe listings are looked up with the OpenFIGI mapping API."
+    )]
+    ResolveIsin {
+        #[clap(help = "ISIN, WKN or CUSIP of the security, e.g. IE00BJ0KDQ92")]
+        identifier: Identifier,
         #[clap(
-            short,
             long,
-            default_value = ".",
-            help = "What character to use as decimal separator"
+            default_value = "alpha-vantage",
+            possible_values = ProviderKind::NAMES,
+            help = "Which service to find symbols for"
         )]
-        separator: char,
+        provider: ProviderKind,
         #[clap(
-            short,
             long,
-            help = "Whether to place the currency symbol before or after the amount."
+            env = "HLEDGER_GET_MARKET_PRICES_OPENFIGI_URL",
+            default_value = figi::DEFAULT_URL,
+            help = "Base URL of the OpenFIGI compatible mapping API. An API key can be given in OPENFIGI_API_KEY"
         )]
-        commodity_symbol_before: bool,
+        openfigi_url: String,
+        #[clap(flatten)]
+        options: SearchArgs,
+    },
+    #[clap(about = "Outputs historic market prices of a stock in a hledger compatible format.")]
+    History {
+        #[clap(help = "Symbol of the stock as given by the `history` subcommand")]
+        stock_symbol: String,
+        #[clap(help = "Commodity name to use for the stock")]
+        stock_commodity_name: String,
+        #[clap(help = "Commodity name to use for the currency the market prices is denoted in")]
+        currency_commodity_name: String,
+        #[clap(flatten)]
+        options: HistoryArgs,
     },
+    #[clap(
+        about = "Outputs historic exchange rates of a currency pair in a hledger compatible format."
+    )]
+    FxHistory {
+        #[clap(help = "ISO code of the currency to get the exchange rate of, e.g. USD")]
+        from: String,
+        #[clap(help = "ISO code of the currency the exchange rate is denoted in, e.g. EUR")]
+        to: String,
+        #[clap(help = "Commodity name to use for the `from` currency")]
+        from_commodity_name: String,
+        #[clap(help = "Commodity name to use for the `to` currency")]
+        to_commodity_name: String,
+        #[clap(flatten)]
+        options: HistoryArgs,
+    },
+    #[clap(
+        about = "Outputs historic prices of a cryptocurrency in a hledger compatible format.\nThere are prices for every day, including weekends."
+    )]
+    CryptoHistory {
+        #[clap(help = "Symbol of the cryptocurrency, e.g. BTC")]
+        symbol: String,
+        #[clap(help = "ISO code of the currency the prices are denoted in, e.g. EUR")]
+        market: String,
+        #[clap(help = "Commodity name to use for the cryptocurrency")]
+        commodity_name: String,
+        #[clap(help = "Commodity name to use for the currency the prices are denoted in")]
+        market_commodity_name: String,
+        #[clap(flatten)]
+        options: HistoryArgs,
+    },
+    #[clap(
+        about = "Fetches new market prices of all commodities in the configuration file.\nPrices are appended to the configured output files, or printed if there are none."
+    )]
+    Update,
+}
+
+/// Filtering and output options of subcommands listing symbol search results.
+#[derive(Args, Debug)]
+struct SearchArgs {
+    #[clap(
+        long,
+        default_value = "table",
+        possible_values = SearchFormat::NAMES,
+        help = "Whether to print a table, JSON or CSV"
+    )]
+    format: SearchFormat,
+    #[clap(
+        long,
+        conflicts_with = "format",
+        help = "Choose one of the listings interactively and add it to the configuration file"
+    )]
+    pick: bool,
+    #[clap(long, help = "Only show listings in this region, e.g. XETRA")]
+    region: Option<String>,
+    #[clap(long = "type", help = "Only show listings of this type, e.g. ETF")]
+    asset_type: Option<String>,
+    #[clap(
+        long,
+        help = "Only show listings traded in the currency with this ISO code, e.g. EUR"
+    )]
+    currency: Option<String>,
+    #[clap(
+        long,
+        multiple_occurrences = true,
+        help = "Show listings in this region first; can be given several times, the most preferred region first [default: preferred-regions of the configuration file]"
+    )]
+    prefer_region: Vec<String>,
+}
+
+impl SearchArgs {
+    /// Returns the filter described by the arguments, preferring the regions of `config`
+    /// unless others are given.
+    fn to_filter(&self, config: &Config) -> SearchFilter {
+        SearchFilter {
+            region: self.region.clone(),
+            asset_type: self.asset_type.clone(),
+            currency: self.currency.clone(),
+            preferred_regions: if self.prefer_region.is_empty() {
+                config.preferred_regions.clone()
+            } else {
+                self.prefer_region.clone()
+            },
+        }
+    }
+
+    /// Prints the `listings` found for `query` or, with `--pick`, lets the user add one of
+    /// them to the configuration file at `config`.
+    fn show(
+        &self,
+        query: &str,
+        listings: &[SymbolMatch],
+        main_journal: Option<&str>,
+        config: Option<PathBuf>,
+    ) -> Result<()> {
+        if self.pick {
+            pick_listing(query, listings, main_journal, config)
+        } else {
+            print!("{}", self.format.render(listings)?);
+            Ok(())
+        }
+    }
+}
+
+/// Options shared by all subcommands outputting market prices.
+#[derive(Args, Debug)]
+struct HistoryArgs {
+    #[clap(
+        short,
+        long,
+        help = "Number of digits after the decimal point to return."
+    )]
+    decimal_digits: Option<usize>,
+    #[clap(
+        long,
+        default_value = "half-even",
+        possible_values = RoundingMode::NAMES,
+        help = "How to round prices with more digits than given by --decimal-digits"
+    )]
+    rounding: RoundingMode,
+    #[clap(
+        short,
+        long,
+        help = "What character to use as decimal separator [default: .]"
+    )]
+    separator: Option<char>,
+    #[clap(
+        short = 'g',
+        long,
+        help = "What character to use to separate groups of digits, e.g. `,` for 31,245.67"
+    )]
+    digit_group_separator: Option<char>,
+    #[clap(
+        long,
+        possible_values = DigitGrouping::NAMES,
+        help = "Whether to group digits by thousands or like in India (31,24,567.89) [default: thousands]"
+    )]
+    digit_grouping: Option<DigitGrouping>,
+    #[clap(
+        short,
+        long,
+        conflicts_with = "commodity-symbol-after",
+        help = "Place the currency symbol before the amount"
+    )]
+    commodity_symbol_before: bool,
+    #[clap(
+        long,
+        help = "Place the currency symbol after the amount, even if the journal places it before"
+    )]
+    commodity_symbol_after: bool,
+    #[clap(
+        long,
+        default_value = "hledger",
+        possible_values = OutputFormat::NAMES,
+        help = "Whether to write hledger `P` directives, beancount `price` directives, Ledger `P` directives with times, or all prices of each day as CSV or JSON Lines"
+    )]
+    format: OutputFormat,
+    #[clap(
+        long,
+        help = "Character separating year, month and day for hledger and Ledger [default: - for hledger, / for Ledger]"
+    )]
+    date_separator: Option<char>,
+    #[clap(
+        long,
+        parse(try_from_str),
+        help = "Time of day written into Ledger price directives, e.g. 17:30, or `market-close` for the closing time of the exchange in its time zone [default: 00:00:00]"
+    )]
+    time: Option<PriceTime>,
+    #[clap(
+        long,
+        default_value = "alpha-vantage",
+        possible_values = ProviderKind::NAMES,
+        help = "Which service to get the market prices from"
+    )]
+    provider: ProviderKind,
+    #[clap(
+        short,
+        long,
+        parse(from_os_str),
+        help = "Write the prices to this file instead of stdout"
+    )]
+    output: Option<PathBuf>,
+    #[clap(
+        long,
+        requires = "output",
+        help = "Only fetch prices newer than the ones already in the output file and append them to it"
+    )]
+    append: bool,
+    #[clap(
+        short,
+        long,
+        parse(try_from_str = parse_date_argument),
+        conflicts_with_all = &["period", "last"],
+        help = "Only output prices on or after this date, e.g. 2022-01-07, 2022-01 or lastmonth"
+    )]
+    begin: Option<NaiveDate>,
+    #[clap(
+        short,
+        long,
+        parse(try_from_str = parse_date_argument),
+        conflicts_with = "period",
+        help = "Only output prices before this date"
+    )]
+    end: Option<NaiveDate>,
+    #[clap(
+        long,
+        parse(try_from_str = parse_last_argument),
+        conflicts_with = "period",
+        help = "Only output prices of this recent time span, e.g. `30 days` or `4 weeks`"
+    )]
+    last: Option<DateRange>,
+    #[clap(
+        short,
+        long,
+        parse(try_from_str = parse_period_argument),
+        help = "Only output prices in this hledger period expression, e.g. 2022, lastmonth or 2021-01..2021-06"
+    )]
+    period: Option<DateRange>,
+    #[clap(
+        long,
+        default_value = "close",
+        possible_values = PriceField::NAMES,
+        help = "Which of the prices of a trading day to use"
+    )]
+    price_field: PriceField,
+    #[clap(
+        long,
+        default_value = "daily",
+        possible_values = Frequency::NAMES,
+        help = "Output only one price per week, month, quarter or year"
+    )]
+    frequency: Frequency,
+    #[clap(
+        long,
+        default_value = "last",
+        possible_values = Aggregation::NAMES,
+        help = "Whether to use the price of the last trading day of each period or the average price"
+    )]
+    aggregation: Aggregation,
+    #[clap(
+        long,
+        help = "Convert the prices into this currency, e.g. EUR, using the exchange rate of each day. The currency commodity name has to denote this currency then"
+    )]
+    convert_to: Option<String>,
+    #[clap(
+        long,
+        default_value = "1",
+        help = "Multiply the prices by this factor, e.g. 0.01 for prices in pence, or `auto` to convert prices quoted in pence, South African cents or Israeli agorot into pounds, rand or shekels"
+    )]
+    scale: Scale,
+}
+
+impl HistoryArgs {
+    /// Returns the commodity described by the arguments together with the range of days
+    /// to output.
+    fn to_commodity(
+        &self,
+        asset_class: AssetClass,
+        symbol: String,
+        commodity: String,
+        currency: String,
+    ) -> (CommodityConfig, DateRange) {
+        let range = self.period.or(self.last).unwrap_or_default();
+        let range = DateRange {
+            begin: self.begin.or(range.begin),
+            end: self.end.or(range.end),
+        };
+        let commodity = CommodityConfig {
+            asset_class,
+            symbol,
+            commodity,
+            currency,
+            provider: self.provider,
+            price_field: self.price_field,
+            frequency: self.frequency,
+            aggregation: self.aggregation,
+            convert_to: self.convert_to.clone(),
+            scale: self.scale,
+            format: self.format,
+            date_separator: self.date_separator,
+            time: self.time,
+            style: AmountStyle {
+                decimal_separator: self.separator,
+                decimal_digits: self.decimal_digits,
+                digit_group_separator: self.digit_group_separator,
+                digit_grouping: self.digit_grouping,
+                currency_before: match (self.commodity_symbol_before, self.commodity_symbol_after) {
+                    (true, _) => Some(true),
+                    (_, true) => Some(false),
+                    _ => None,
+                },
+                currency_spaced: None,
+                rounding: self.rounding,
+            },
+            output: None,
+            extra: BTreeMap::new(),
+        };
+        (commodity, range)
+    }
+
+    /// Outputs the history of the commodity described by the arguments, formatting prices
+    /// like the amounts in `main_journal` unless the arguments say otherwise.
+    async fn write(
+        &self,
+        settings: &ProviderSettings,
+        main_journal: Option<&str>,
+        asset_class: AssetClass,
+        symbol: String,
+        commodity: String,
+        currency: String,
+    ) -> Result<()> {
+        let (commodity, range) = self.to_commodity(asset_class, symbol, commodity, currency);
+        let commodity = with_journal_style(commodity, main_journal);
+        write_history(
+            settings,
+            &commodity,
+            range,
+            self.output.as_deref(),
+            self.append,
+        )
+        .await
+    }
+}
+
+/// Fills the style settings `commodity` doesn't specify with the ones its currency has in
+/// `main_journal`.
+fn with_journal_style(
+    mut commodity: CommodityConfig,
+    main_journal: Option<&str>,
+) -> CommodityConfig {
+    if let Some(style) = main_journal
+        .and_then(|main_journal| journal::commodity_style(main_journal, &commodity.currency))
+    {
+        commodity.style = commodity.style.or(&style);
+    }
+    commodity
+}
+
+/// Prints an explanation of `error` that tells the user what to do about it.
+fn report_error(error: &Error) {
+    match error {
+        Error::MissingApiKey { variable } => eprintln!("Environment variable {variable} is not set.\nPlease set this variable to your Alpha Vantage API key and try again."),
+        Error::InvalidApiKey(message) => eprintln!("The API key was not accepted: {message}\nPlease recheck whether HLEDGER_GET_MARKET_PRICES_API_KEY is indeed set to your API key."),
+        Error::Config { path, message } => eprintln!("The configuration file {} is invalid: {message}", path.display()),
+        Error::InvalidStyle(message) => eprintln!("The prices can't be written like this: {message}"),
+        Error::InvalidCommodity { name, message } => eprintln!("The commodity name `{name}` can't be used: {message}"),
+        Error::Unsupported(message) => eprintln!("This is not supported: {message}"),
+        Error::UnknownSymbol { symbol, message } => eprintln!("The symbol `{symbol}` is not known to the provider: {message}\nUse the `search-stock-symbol` subcommand to find the correct symbol."),
+        Error::RateLimited(message) => eprintln!("The provider refused the request because too many requests were made: {message}\nPlease try again later."),
+        Error::Network(message) => eprintln!("The provider could not be reached: {message}\nPlease check your network connection and try again later."),
+        Error::NotCached(message) => eprintln!("Offline mode was requested, but the response is not cached: {message}\nRun the command once without --offline to fill the cache."),
+        Error::MalformedResponse(message) => eprintln!("The provider returned a response that could not be understood: {message}"),
+        Error::Io { path, source } => eprintln!("Could not access {}: {source}", path.display()),
+        Error::Internal(message) => eprintln!("An unexpected problem occured that the application can't recover from.\n\nDetails about the error are below. If you believe the invocation of hledger-get-market-prices is correct, I'd appreciate a bug report at {}/issues/new.\n\nError message: {message}", env!("CARGO_PKG_REPOSITORY")),
+    }
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    match App::parse().command {
-        Command::SearchStockSymbol { search_query } => {
-            hledger_get_market_prices::search_stock_symbol(search_query).await
+async fn main() -> ExitCode {
+    match run(App::parse()).await {
+        Ok(exit_code) => exit_code,
+        Err(error) => {
+            report_error(&error);
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
+
+fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+fn parse_date_argument(text: &str) -> std::result::Result<NaiveDate, String> {
+    period::parse_date(text, today())
+}
+
+fn parse_last_argument(text: &str) -> std::result::Result<DateRange, String> {
+    period::parse_last(text, today())
+}
+
+fn parse_period_argument(text: &str) -> std::result::Result<DateRange, String> {
+    period::parse_period(text, today())
+}
+
+/// Writes `text` to `output`, or to stdout if there is none.
+fn write_output(output: Option<&Path>, text: &str) -> Result<()> {
+    output.map_or_else(
+        || {
+            print!("{text}");
+            Ok(())
+        },
+        |path| {
+            std::fs::write(path, text).map_err(|source| Error::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+        },
+    )
+}
+
+/// Fetches the prices of `commodity` within `range` and writes them to stdout or `output`.
+///
+/// With `append`, only prices newer than the ones already in `output` are fetched and
+/// appended to it.
+async fn write_history(
+    settings: &ProviderSettings,
+    commodity: &CommodityConfig,
+    range: DateRange,
+    output: Option<&Path>,
+    append: bool,
+) -> Result<()> {
+    match output {
+        Some(path) if append => history::append(settings, commodity, range, path).await,
+        _ => write_output(output, &history::render(settings, commodity, range).await?),
+    }
+}
+
+/// Loads the configuration file given on the command line or the default one.
+fn load_config(path: Option<PathBuf>) -> Result<Config> {
+    let path = path
+        .or_else(Config::default_path)
+        .ok_or_else(|| Error::Config {
+            path: PathBuf::new(),
+            message: "no configuration directory found, please pass --config".to_string(),
+        })?;
+    Config::load(&path)
+}
+
+/// Reads the configuration file at `path` or, if none is given, the one in the user's
+/// configuration directory. A file that doesn't exist is treated as empty.
+fn load_optional_config(path: Option<PathBuf>) -> Result<Config> {
+    path.or_else(Config::default_path)
+        .filter(|path| path.exists())
+        .map_or_else(|| Ok(Config::default()), |path| Config::load(&path))
+}
+
+/// Asks `question` on stderr and returns the answer read from stdin, or `default` if the
+/// answer is empty.
+fn prompt(question: &str, default: &str) -> Result<String> {
+    if default.is_empty() {
+        eprint!("{question}: ");
+    } else {
+        eprint!("{question} [{default}]: ");
+    }
+    let mut answer = String::new();
+    let read = std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(|source| Error::Io {
+            path: PathBuf::from("standard input"),
+            source,
+        })?;
+    if read == 0 {
+        return Err(Error::Unsupported(
+            "--pick needs answers on standard input".to_string(),
+        ));
+    }
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+/// Asks `question` like [`prompt`] until the answer isn't empty.
+fn prompt_name(question: &str, default: &str) -> Result<String> {
+    loop {
+        let answer = prompt(question, default)?;
+        if !answer.is_empty() {
+            return Ok(answer);
+        }
+        eprintln!("Please enter a name.");
+    }
+}
+
+/// Lets the user choose one of `listings` and the commodity names to use for it, and adds
+/// it to the configuration file at `config`.
+///
+/// The style of the amounts is taken from the currency in `main_journal`, unless the user
+/// asks for a different number of decimal digits.
+fn pick_listing(
+    search_query: &str,
+    listings: &[SymbolMatch],
+    main_journal: Option<&str>,
+    config: Option<PathBuf>,
+) -> Result<()> {
+    if listings.is_empty() {
+        return Err(Error::UnknownSymbol {
+            symbol: search_query.to_string(),
+            message: "the search didn't find any listings".to_string(),
+        });
+    }
+    let path = config
+        .or_else(Config::default_path)
+        .ok_or_else(|| Error::Config {
+            path: PathBuf::new(),
+            message: "no configuration directory found, please pass --config".to_string(),
+        })?;
+
+    let tracked = load_optional_config(Some(path.clone()))?.commodities;
+    let tracked_as = |listing: &SymbolMatch| {
+        tracked
+            .iter()
+            .find(|commodity| commodity.symbol.eq_ignore_ascii_case(&listing.symbol))
+            .map(|commodity| commodity.commodity.clone())
+    };
+
+    for (number, listing) in listings.iter().enumerate() {
+        let note = tracked_as(listing)
+            .map(|commodity| format!(", already tracked as {commodity}"))
+            .unwrap_or_default();
+        eprintln!(
+            "{:>3}) {} – {} ({}, {}{note})",
+            number + 1,
+            listing.symbol,
+            listing.name,
+            listing.region,
+            listing.currency
+        );
+    }
+    let listing = loop {
+        let answer = prompt("Listing", "1")?;
+        match answer.parse::<usize>() {
+            Ok(number) if (1..=listings.len()).contains(&number) => {
+                let listing = &listings[number - 1];
+                match tracked_as(listing) {
+                    Some(commodity) => eprintln!(
+                        "{} is already tracked as {commodity}, please choose another listing.",
+                        listing.symbol
+                    ),
+                    None => break listing,
+                }
+            }
+            _ => eprintln!("Please enter a number from 1 to {}.", listings.len()),
+        }
+    };
+
+    let ticker = listing.symbol.split('.').next().unwrap_or(&listing.symbol);
+    let commodity = prompt_name("Commodity name", ticker)?;
+    let (currency_code, auto_scale) = scale::minor_unit(&listing.currency)
+        .map_or((listing.currency.as_str(), false), |(major, _)| {
+            (major, true)
+        });
+    let currency = prompt_name("Currency commodity name", currency_code)?;
+
+    let mut style = main_journal
+        .and_then(|main_journal| journal::commodity_style(main_journal, &currency))
+        .unwrap_or_default();
+    let default_digits = style
+        .decimal_digits
+        .map(|digits| digits.to_string())
+        .unwrap_or_default();
+    style.decimal_digits = loop {
+        let answer = prompt(
+            "Decimal digits, empty for as many as needed",
+            &default_digits,
+        )?;
+        if answer.is_empty() {
+            break None;
+        }
+        match answer.parse() {
+            Ok(digits) => break Some(digits),
+            Err(_) => eprintln!("Please enter a number or nothing."),
+        }
+    };
+
+    Config::append_commodity(
+        &path,
+        &NewCommodity {
+            symbol: listing.symbol.clone(),
+            commodity,
+            currency,
+            auto_scale,
+            style,
+        },
+    )?;
+    eprintln!("Added {} to {}.", listing.symbol, path.display());
+    Ok(())
+}
+
+/// Reads the journal given with `--journal` or, if there is none, the one in `LEDGER_FILE`,
+/// if the command writes amounts.
+///
+/// Many hledger users have `LEDGER_FILE` set for other purposes, so failing to read it
+/// only prints a warning and amounts are then written without its style.
+fn read_main_journal(app: &App) -> Result<Option<String>> {
+    let formats_amounts = match &app.command {
+        Command::SearchStockSymbol { options, .. } | Command::ResolveIsin { options, .. } => {
+            options.pick
+        }
+        _ => true,
+    };
+    if !formats_amounts {
+        return Ok(None);
+    }
+    if let Some(path) = &app.journal {
+        return journal::read(path).map(Some);
+    }
+    let Some(path) = std::env::var_os("LEDGER_FILE").filter(|path| !path.is_empty()) else {
+        return Ok(None);
+    };
+    match journal::read(Path::new(&path)) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(error) => {
+            eprintln!("Warning: ignoring the journal in LEDGER_FILE: {error}");
+            Ok(None)
+        }
+    }
+}
+
+/// Returns the settings shared by all providers, as given by the global options.
+fn provider_settings(app: &App) -> ProviderSettings {
+    ProviderSettings {
+        rate_limiter: Arc::new(RateLimiter::new(app.requests_per_minute)),
+        max_retries: app.max_retries,
+        cache: Cache::default_directory().map(|directory| {
+            let mode = if app.offline {
+                CacheMode::Offline
+            } else if app.refresh {
+                CacheMode::Refresh
+            } else {
+                CacheMode::Normal
+            };
+            Cache::new(
+                directory,
+                Duration::from_secs(app.cache_ttl.saturating_mul(60)),
+                mode,
+            )
+        }),
+    }
+}
+
+/// Fetches new prices of all commodities in the configuration file, reporting failures
+/// without stopping. Returns the exit code of the last failure.
+async fn update(
+    settings: &ProviderSettings,
+    main_journal: Option<&str>,
+    config: Option<PathBuf>,
+) -> Result<ExitCode> {
+    let config = load_config(config)?;
+    let mut exit_code = ExitCode::SUCCESS;
+    for commodity in &config.commodities {
+        if let Err(error) = write_history(
+            settings,
+            &with_journal_style(commodity.clone(), main_journal),
+            DateRange::default(),
+            commodity.output(&config),
+            true,
+        )
+        .await
+        {
+            eprintln!("Could not update {}:", commodity.commodity);
+            report_error(&error);
+            exit_code = ExitCode::from(error.exit_code());
+        }
+    }
+    Ok(exit_code)
+}
+
+async fn run(app: App) -> Result<ExitCode> {
+    let settings = provider_settings(&app);
+    let main_journal = read_main_journal(&app)?;
+    let main_journal = main_journal.as_deref();
+
+    match app.command {
+        Command::SearchStockSymbol {
+            search_query,
+            provider,
+            options,
+        } => {
+            let filter = options.to_filter(&load_optional_config(app.config.clone())?);
+            let provider = provider.create(&settings)?;
+            let results = filter.apply(
+                hledger_get_market_prices::search_stock_symbol(provider.as_ref(), &search_query)
+                    .await?,
+            );
+            options.show(&search_query, &results, main_journal, app.config)?;
+        }
+        Command::ResolveIsin {
+            identifier,
+            provider,
+            openfigi_url,
+            options,
+        } => {
+            let filter = options.to_filter(&load_optional_config(app.config.clone())?);
+            let figi = OpenFigi::from_env(&openfigi_url, settings.clone())?;
+            let provider = provider.create(&settings)?;
+            let results = filter.apply(
+                hledger_get_market_prices::resolve_identifier(
+                    &figi,
+                    provider.as_ref(),
+                    &identifier,
+                )
+                .await?,
+            );
+            options.show(identifier.value(), &results, main_journal, app.config)?;
         }
         Command::History {
             stock_symbol,
             stock_commodity_name,
-            decimal_digits,
-            separator,
             currency_commodity_name,
-            commodity_symbol_before,
+            options,
         } => {
-            hledger_get_market_prices::get_history_for_stock(
-                stock_symbol,
-                stock_commodity_name,
-                currency_commodity_name,
-                separator,
-                decimal_digits,
-                commodity_symbol_before,
-            )
-            .await
+            options
+                .write(
+                    &settings,
+                    main_journal,
+                    AssetClass::Security,
+                    stock_symbol,
+                    stock_commodity_name,
+                    currency_commodity_name,
+                )
+                .await?;
         }
+        Command::FxHistory {
+            from,
+            to,
+            from_commodity_name,
+            to_commodity_name,
+            options,
+        } => {
+            options
+                .write(
+                    &settings,
+                    main_journal,
+                    AssetClass::Fx,
+                    format!("{from}/{to}"),
+                    from_commodity_name,
+                    to_commodity_name,
+                )
+                .await?;
+        }
+        Command::CryptoHistory {
+            symbol,
+            market,
+            commodity_name,
+            market_commodity_name,
+            options,
+        } => {
+            options
+                .write(
+                    &settings,
+                    main_journal,
+                    AssetClass::Crypto,
+                    format!("{symbol}/{market}"),
+                    commodity_name,
+                    market_commodity_name,
+                )
+                .await?;
+        }
+        Command::Update => return update(&settings, main_journal, app.config).await,
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
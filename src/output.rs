@@ -0,0 +1,816 @@
+//! Rendering of market prices as journal text.
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use chrono::{NaiveDate, NaiveTime};
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::provider::{Quote, SymbolMatch};
+use crate::{Error, MarketPrice};
+
+/// How the amount of a market price is written.
+///
+/// Settings that are `None` fall back to the style of another source, see
+/// [`AmountStyle::or`], and finally to a sensible default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct AmountStyle {
+    /// Character used as decimal separator, `.` by default
+    pub decimal_separator: Option<char>,
+    /// Character separating groups of digits before the decimal separator, none by default
+    pub digit_group_separator: Option<char>,
+    /// How digits are grouped, by thousands by default
+    pub digit_grouping: Option<DigitGrouping>,
+    /// Number of digits after the decimal separator, or `None` to print as many as needed
+    pub decimal_digits: Option<usize>,
+    /// Whether the currency is written before the amount (`€12.3`) instead of after it
+    /// (`12.3 €`), after it by default
+    pub currency_before: Option<bool>,
+    /// Whether currency and amount are separated by a space, see
+    /// [`hledger_price_directive`] for the default
+    pub currency_spaced: Option<bool>,
+    /// How amounts are rounded to `decimal_digits`
+    pub rounding: RoundingMode,
+}
+
+impl AmountStyle {
+    /// Fills the settings that are not set in this style with the ones of `fallback`.
+    ///
+    /// The digit group separator of `fallback` is not used if it is the decimal separator
+    /// of this style.
+    #[must_use]
+    pub fn or(self, fallback: &Self) -> Self {
+        let decimal_separator = self.decimal_separator.or(fallback.decimal_separator);
+        Self {
+            decimal_separator,
+            digit_group_separator: self.digit_group_separator.or_else(|| {
+                fallback
+                    .digit_group_separator
+                    .filter(|&separator| separator != decimal_separator.unwrap_or('.'))
+            }),
+            digit_grouping: self.digit_grouping.or(fallback.digit_grouping),
+            decimal_digits: self.decimal_digits.or(fallback.decimal_digits),
+            currency_before: self.currency_before.or(fallback.currency_before),
+            currency_spaced: self.currency_spaced.or(fallback.currency_spaced),
+            rounding: self.rounding,
+        }
+    }
+
+    /// Checks that amounts written in this style can be read back unambiguously.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the digit group separator is a digit or the decimal separator.
+    pub fn validate(&self) -> crate::Result<()> {
+        let decimal_separator = self.decimal_separator.unwrap_or('.');
+        if decimal_separator.is_ascii_digit() {
+            return Err(Error::InvalidStyle(format!(
+                "the decimal separator `{decimal_separator}` is a digit"
+            )));
+        }
+        match self.digit_group_separator {
+            Some(separator) if separator == decimal_separator => Err(Error::InvalidStyle(format!(
+                "`{separator}` can't be both the decimal and the digit group separator"
+            ))),
+            Some(separator) if separator.is_ascii_digit() => Err(Error::InvalidStyle(format!(
+                "the digit group separator `{separator}` is a digit"
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How the digits before the decimal separator are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DigitGrouping {
+    /// Groups of three digits, e.g. `1,234,567`
+    #[default]
+    Thousands,
+    /// A group of three digits followed by groups of two, e.g. `12,34,567`
+    Indian,
+}
+
+impl DigitGrouping {
+    /// Names accepted by [`DigitGrouping::from_str`].
+    pub const NAMES: &'static [&'static str] = &["thousands", "indian"];
+
+    /// Whether a separator is placed in front of the last `digits` digits of the integer
+    /// part.
+    const fn separates(self, digits: usize) -> bool {
+        match self {
+            Self::Thousands => digits.is_multiple_of(3),
+            Self::Indian => digits >= 3 && !digits.is_multiple_of(2),
+        }
+    }
+}
+
+impl FromStr for DigitGrouping {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "thousands" => Ok(Self::Thousands),
+            "indian" => Ok(Self::Indian),
+            _ => Err(format!(
+                "unknown digit grouping `{}`, expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// The syntax price directives are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JournalFormat {
+    /// hledger `P` directives
+    #[default]
+    Hledger,
+    /// beancount `price` directives
+    Beancount,
+    /// Ledger `P` directives, which contain a time
+    Ledger,
+}
+
+impl JournalFormat {
+    /// Checks that `name` can be used as a commodity name in this format.
+    ///
+    /// # Errors
+    ///
+    /// Fails for beancount if `name` is not made of 2 to 24 capital letters, digits and
+    /// the characters `'._-`, starting with a letter and ending with a letter or digit.
+    /// For hledger and Ledger, it fails if `name` contains a double quote, as quoted names
+    /// can't contain one, unless the whole name is already quoted.
+    pub fn validate_commodity(self, name: &str) -> crate::Result<()> {
+        match self {
+            Self::Hledger | Self::Ledger => {
+                let unquoted = name
+                    .strip_prefix('"')
+                    .and_then(|name| name.strip_suffix('"'))
+                    .unwrap_or(name);
+                if unquoted.contains('"') {
+                    Err(Error::InvalidCommodity {
+                        name: name.to_string(),
+                        message: "commodity names can't contain double quotes, except around the whole name".to_string(),
+                    })
+                } else {
+                    Ok(())
+                }
+            }
+            Self::Beancount => {
+                let valid = (2..=24).contains(&name.len())
+                    && name.starts_with(|character: char| character.is_ascii_uppercase())
+                    && name.ends_with(|character: char| {
+                        character.is_ascii_uppercase() || character.is_ascii_digit()
+                    })
+                    && name.chars().all(|character| {
+                        character.is_ascii_uppercase()
+                            || character.is_ascii_digit()
+                            || "'._-".contains(character)
+                    });
+                if valid {
+                    Ok(())
+                } else {
+                    Err(Error::InvalidCommodity {
+                        name: name.to_string(),
+                        message: "beancount commodities consist of 2 to 24 capital letters, digits and the characters '._-, start with a letter and end with a letter or digit".to_string(),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Formats `price` as a directive of this format.
+    #[must_use]
+    pub fn price_directive(
+        self,
+        price: &MarketPrice,
+        style: &AmountStyle,
+        date_style: &DateStyle,
+    ) -> String {
+        match self {
+            Self::Hledger => hledger_price_directive(price, style, date_style),
+            Self::Beancount => beancount_price_directive(price, style),
+            Self::Ledger => ledger_price_directive(price, style, date_style),
+        }
+    }
+}
+
+/// What prices are written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Price directives of a plain text accounting tool
+    Journal(JournalFormat),
+    /// Comma separated values with a header line and all prices of each day
+    Csv,
+    /// One JSON object with all prices of each day per line
+    Jsonl,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        Self::Journal(JournalFormat::default())
+    }
+}
+
+impl OutputFormat {
+    /// Names accepted by [`OutputFormat::from_str`].
+    pub const NAMES: &'static [&'static str] = &["hledger", "beancount", "ledger", "csv", "jsonl"];
+
+    /// Checks that `name` can be used as a commodity name in this format, see
+    /// [`JournalFormat::validate_commodity`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the journal format doesn't allow `name`.
+    pub fn validate_commodity(self, name: &str) -> crate::Result<()> {
+        match self {
+            Self::Journal(format) => format.validate_commodity(name),
+            Self::Csv | Self::Jsonl => Ok(()),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "hledger" => Ok(Self::Journal(JournalFormat::Hledger)),
+            "beancount" => Ok(Self::Journal(JournalFormat::Beancount)),
+            "ledger" => Ok(Self::Journal(JournalFormat::Ledger)),
+            "csv" => Ok(Self::Csv),
+            "jsonl" => Ok(Self::Jsonl),
+            _ => Err(format!(
+                "unknown output format `{}`, expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for OutputFormat {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// How the date of a price directive is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateStyle {
+    /// Character separating year, month and day, `-` for hledger and `/` for Ledger by
+    /// default
+    pub separator: Option<char>,
+    /// Time of day written after the date, only used for Ledger, `00:00:00` by default
+    pub time: Option<NaiveTime>,
+}
+
+impl DateStyle {
+    /// Checks that hledger and Ledger can read dates written in this style.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the separator is not one of `-`, `/` and `.`.
+    pub fn validate(&self) -> crate::Result<()> {
+        match self.separator {
+            Some(separator) if !"-/.".contains(separator) => Err(Error::InvalidStyle(format!(
+                "`{separator}` can't separate the parts of a date, use `-`, `/` or `.`"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn format_date(&self, date: NaiveDate, default_separator: char) -> String {
+        let separator = self.separator.unwrap_or(default_separator);
+        date.format(&format!("%Y{separator}%m{separator}%d"))
+            .to_string()
+    }
+}
+
+/// The time of day prices are recorded at in Ledger's price database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceTime {
+    /// A fixed time of day
+    At(NaiveTime),
+    /// The time the exchange closes, in the time zone of the exchange
+    MarketClose,
+}
+
+impl FromStr for PriceTime {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text == "market-close" {
+            return Ok(Self::MarketClose);
+        }
+        NaiveTime::parse_from_str(text, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(text, "%H:%M"))
+            .map(Self::At)
+            .map_err(|_| format!("invalid time `{text}`, expected e.g. `17:30` or `market-close`"))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for PriceTime {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// How an amount with more digits than shown is rounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoundingMode {
+    /// To the nearest number, and to the even one if both are equally near, like hledger
+    #[default]
+    HalfEven,
+    /// To the nearest number, and away from zero if both are equally near
+    HalfUp,
+    /// To the nearest number, and towards zero if both are equally near
+    HalfDown,
+    /// Away from zero
+    Up,
+    /// Towards zero, i.e. cutting off the remaining digits
+    Down,
+}
+
+impl RoundingMode {
+    /// Names accepted by [`RoundingMode::from_str`].
+    pub const NAMES: &'static [&'static str] = &["half-even", "half-up", "half-down", "up", "down"];
+
+    const fn strategy(self) -> RoundingStrategy {
+        match self {
+            Self::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Self::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Self::HalfDown => RoundingStrategy::MidpointTowardZero,
+            Self::Up => RoundingStrategy::AwayFromZero,
+            Self::Down => RoundingStrategy::ToZero,
+        }
+    }
+}
+
+impl FromStr for RoundingMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "half-even" => Ok(Self::HalfEven),
+            "half-up" => Ok(Self::HalfUp),
+            "half-down" => Ok(Self::HalfDown),
+            "up" => Ok(Self::Up),
+            "down" => Ok(Self::Down),
+            _ => Err(format!(
+                "unknown rounding mode `{}`, expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Returns the comment line put at the top of generated output.
+#[must_use]
+pub fn generated_by_comment() -> String {
+    format!(
+        "; Generated by {}",
+        concat!(env!("CARGO_PKG_NAME"), " V", env!("CARGO_PKG_VERSION"))
+    )
+}
+
+/// Formats `amount` according to `style`, without the currency.
+///
+/// Without a fixed number of decimal digits, the amount is written with as many digits as
+/// it has, but without trailing zeros.
+#[must_use]
+pub fn format_amount(amount: Decimal, style: &AmountStyle) -> String {
+    let amount = style.decimal_digits.map_or_else(
+        || amount.normalize(),
+        |decimal_digits| {
+            let decimal_digits = u32::try_from(decimal_digits).unwrap_or(u32::MAX);
+            let mut rounded =
+                amount.round_dp_with_strategy(decimal_digits, style.rounding.strategy());
+            // Pads with zeros up to the requested number of digits.
+            rounded.rescale(decimal_digits);
+            rounded
+        },
+    );
+    let amount_string = amount.to_string();
+    let (sign, amount_string) = amount_string
+        .strip_prefix('-')
+        .map_or(("", amount_string.as_str()), |unsigned| ("-", unsigned));
+    let (integer, fraction) = amount_string
+        .split_once('.')
+        .map_or((amount_string, None), |(integer, fraction)| {
+            (integer, Some(fraction))
+        });
+
+    let mut formatted = sign.to_string();
+    let grouping = style.digit_grouping.unwrap_or_default();
+    for (index, digit) in integer.chars().enumerate() {
+        if let Some(separator) = style.digit_group_separator {
+            if index > 0 && grouping.separates(integer.len() - index) {
+                formatted.push(separator);
+            }
+        }
+        formatted.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        formatted.push(style.decimal_separator.unwrap_or('.'));
+        formatted.push_str(fraction);
+    }
+    formatted
+}
+
+/// Returns `name` in double quotes if hledger requires that, i.e. if it contains digits,
+/// spaces or one of the characters `-+.@*;"{}=`. Names that are already quoted are
+/// returned unchanged.
+#[must_use]
+pub fn quote_commodity(name: &str) -> Cow<'_, str> {
+    let is_quoted = name.len() >= 2 && name.starts_with('"') && name.ends_with('"');
+    if !is_quoted
+        && name.chars().any(|character| {
+            character.is_ascii_digit()
+                || character.is_whitespace()
+                || "-+.@*;\"{}=".contains(character)
+        })
+    {
+        Cow::Owned(format!("\"{name}\""))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+/// Formats `price` as an hledger `P` directive.
+///
+/// Unless the style says otherwise, a currency written before the amount is only separated
+/// by a space if it is a word like `EUR` instead of a symbol like `$`. A currency written
+/// directly after the amount is always separated if it starts with `e` or `E`, as hledger
+/// would read it as exponent otherwise.
+#[must_use]
+pub fn hledger_price_directive(
+    price: &MarketPrice,
+    style: &AmountStyle,
+    date_style: &DateStyle,
+) -> String {
+    let date = date_style.format_date(price.date, '-');
+    format!("P {date} {}", price_body(price, style))
+}
+
+/// Formats `price` as a Ledger `P` directive, which has a time after the date.
+///
+/// Amounts are written like in [`hledger_price_directive`].
+#[must_use]
+pub fn ledger_price_directive(
+    price: &MarketPrice,
+    style: &AmountStyle,
+    date_style: &DateStyle,
+) -> String {
+    let date = date_style.format_date(price.date, '/');
+    let time = date_style.time.unwrap_or(NaiveTime::MIN).format("%H:%M:%S");
+    format!("P {date} {time} {}", price_body(price, style))
+}
+
+/// Formats the commodity and amount of a `P` directive.
+fn price_body(price: &MarketPrice, style: &AmountStyle) -> String {
+    let amount = format_amount(price.amount, style);
+    let commodity = quote_commodity(&price.commodity);
+    let currency = quote_commodity(&price.currency);
+    let currency_before = style.currency_before.unwrap_or(false);
+    let spaced = if currency_before {
+        style.currency_spaced.unwrap_or_else(|| {
+            currency.starts_with('"')
+                || currency.chars().count() > 1 && currency.chars().all(char::is_alphabetic)
+        })
+    } else {
+        style.currency_spaced.unwrap_or(true) || currency.starts_with(['e', 'E'])
+    };
+    let space = if spaced { " " } else { "" };
+    if currency_before {
+        format!("{commodity} {currency}{space}{amount}")
+    } else {
+        format!("{commodity} {amount}{space}{currency}")
+    }
+}
+
+/// Formats `price` as a beancount `price` directive.
+///
+/// Beancount only accepts `.` as decimal separator and requires the currency after the
+/// amount, so only the number of decimal digits and the rounding of `style` are used.
+#[must_use]
+pub fn beancount_price_directive(price: &MarketPrice, style: &AmountStyle) -> String {
+    let amount = format_amount(
+        price.amount,
+        &AmountStyle {
+            decimal_digits: style.decimal_digits,
+            rounding: style.rounding,
+            ..AmountStyle::default()
+        },
+    );
+    format!(
+        "{} price {} {amount} {}",
+        price.date, price.commodity, price.currency
+    )
+}
+
+/// The header line of CSV output.
+pub const CSV_HEADER: &str = "date,symbol,commodity,open,high,low,close,volume,currency";
+
+/// Quotes `field` for CSV if it contains a comma, a double quote or a line break.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Formats `quote` of the asset `symbol` as a line of CSV output, see [`CSV_HEADER`].
+///
+/// `commodity` and `currency` are the commodity names the quote is denoted in, like in
+/// [`MarketPrice`]. A missing volume is left empty.
+#[must_use]
+pub fn csv_record(symbol: &str, commodity: &str, currency: &str, quote: &Quote) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{},{}",
+        quote.date,
+        csv_field(symbol),
+        csv_field(commodity),
+        quote.open.normalize(),
+        quote.high.normalize(),
+        quote.low.normalize(),
+        quote.close.normalize(),
+        quote
+            .volume
+            .map(|volume| volume.to_string())
+            .unwrap_or_default(),
+        csv_field(currency)
+    )
+}
+
+/// Formats `quote` of the asset `symbol` as a line of JSON Lines output.
+///
+/// Prices are written as JSON numbers with all their digits. A missing volume is `null`.
+#[must_use]
+pub fn json_line(symbol: &str, commodity: &str, currency: &str, quote: &Quote) -> String {
+    let string = |text: &str| serde_json::Value::from(text).to_string();
+    format!(
+        "{{\"date\":\"{}\",\"symbol\":{},\"commodity\":{},\"open\":{},\"high\":{},\"low\":{},\"close\":{},\"volume\":{},\"currency\":{}}}",
+        quote.date,
+        string(symbol),
+        string(commodity),
+        quote.open.normalize(),
+        quote.high.normalize(),
+        quote.low.normalize(),
+        quote.close.normalize(),
+        quote.volume.map_or_else(|| "null".to_string(), |volume| volume.to_string()),
+        string(currency)
+    )
+}
+
+/// How the listings found by a symbol search are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchFormat {
+    /// A table for people to read
+    #[default]
+    Table,
+    /// A JSON array of objects
+    Json,
+    /// Comma separated values with a header line
+    Csv,
+}
+
+impl SearchFormat {
+    /// Names accepted by [`SearchFormat::from_str`].
+    pub const NAMES: &'static [&'static str] = &["table", "json", "csv"];
+
+    /// Renders `matches` in the order given.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the matches can't be serialized as JSON.
+    pub fn render(self, matches: &[SymbolMatch]) -> crate::Result<String> {
+        match self {
+            Self::Table => Ok(search_table(matches)),
+            Self::Json => serde_json::to_string_pretty(matches)
+                .map(|json| json + "\n")
+                .map_err(|error| Error::Internal(error.to_string())),
+            Self::Csv => Ok(search_csv(matches)),
+        }
+    }
+}
+
+impl FromStr for SearchFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(format!(
+                "unknown search format `{}`, expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+fn search_table(matches: &[SymbolMatch]) -> String {
+    let mut table = format!(
+        "{:>20} | {:>12} | {:>12} | {:8} | {:11} | {:7} | {:>5} – {}\n\n",
+        "Region", "Symbol", "Type", "Currency", "Hours", "Zone", "Score", "Name"
+    );
+    table.extend(matches.iter().map(|listing| {
+        format!(
+            "{:>20} | {:>12} | {:>12} | {:8} | {:>5}-{:5} | {:7} | {:>5.3} – {}\n",
+            listing.region,
+            listing.symbol,
+            listing.asset_type,
+            listing.currency,
+            listing.market_open,
+            listing.market_close,
+            listing.timezone,
+            listing.match_score,
+            listing.name
+        )
+    }));
+    table
+}
+
+fn search_csv(matches: &[SymbolMatch]) -> String {
+    let mut csv =
+        "symbol,name,type,region,currency,market_open,market_close,timezone,match_score\n"
+            .to_string();
+    csv.extend(matches.iter().map(|listing| {
+        format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            csv_field(&listing.symbol),
+            csv_field(&listing.name),
+            csv_field(&listing.asset_type),
+            csv_field(&listing.region),
+            csv_field(&listing.currency),
+            csv_field(&listing.market_open),
+            csv_field(&listing.market_close),
+            csv_field(&listing.timezone),
+            listing.match_score
+        )
+    }));
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(decimal_digits: Option<usize>, rounding: RoundingMode) -> AmountStyle {
+        AmountStyle {
+            decimal_digits,
+            rounding,
+            ..AmountStyle::default()
+        }
+    }
+
+    #[test]
+    fn thousands_are_separated_every_three_digits() {
+        let separated: Vec<usize> = (1..=9)
+            .filter(|&digits| DigitGrouping::Thousands.separates(digits))
+            .collect();
+        assert_eq!(separated, [3, 6, 9]);
+    }
+
+    #[test]
+    fn indian_grouping_separates_the_last_three_digits_then_pairs() {
+        let separated: Vec<usize> = (1..=9)
+            .filter(|&digits| DigitGrouping::Indian.separates(digits))
+            .collect();
+        assert_eq!(separated, [3, 5, 7, 9]);
+
+        let style = AmountStyle {
+            digit_group_separator: Some(','),
+            digit_grouping: Some(DigitGrouping::Indian),
+            ..AmountStyle::default()
+        };
+        let amount: Decimal = "-1234567.891".parse().unwrap();
+        assert_eq!(format_amount(amount, &style), "-12,34,567.891");
+    }
+
+    #[test]
+    fn negative_amounts_keep_the_sign_before_the_groups() {
+        let style = AmountStyle {
+            decimal_separator: Some(','),
+            digit_group_separator: Some('.'),
+            ..AmountStyle::default()
+        };
+        let amount: Decimal = "-123456.5".parse().unwrap();
+        assert_eq!(format_amount(amount, &style), "-123.456,5");
+        let amount: Decimal = "-123.5".parse().unwrap();
+        assert_eq!(format_amount(amount, &style), "-123,5");
+    }
+
+    #[test]
+    fn amounts_are_rounded_with_the_rounding_mode() {
+        let amount: Decimal = "2.345".parse().unwrap();
+        assert_eq!(
+            format_amount(amount, &style(Some(2), RoundingMode::HalfEven)),
+            "2.34"
+        );
+        assert_eq!(
+            format_amount(amount, &style(Some(2), RoundingMode::HalfUp)),
+            "2.35"
+        );
+        assert_eq!(
+            format_amount(amount, &style(Some(2), RoundingMode::Down)),
+            "2.34"
+        );
+        assert_eq!(
+            format_amount(-amount, &style(Some(2), RoundingMode::HalfUp)),
+            "-2.35"
+        );
+    }
+
+    #[test]
+    fn amounts_are_padded_to_the_decimal_digits() {
+        let amount: Decimal = "86.5".parse().unwrap();
+        assert_eq!(
+            format_amount(amount, &style(Some(3), RoundingMode::HalfEven)),
+            "86.500"
+        );
+        assert_eq!(
+            format_amount(amount, &style(Some(0), RoundingMode::HalfEven)),
+            "86"
+        );
+        let amount: Decimal = "86.5000".parse().unwrap();
+        assert_eq!(
+            format_amount(amount, &style(None, RoundingMode::HalfEven)),
+            "86.5"
+        );
+    }
+
+    fn body(commodity: &str, currency: &str, currency_before: bool) -> String {
+        let price = MarketPrice {
+            date: NaiveDate::from_ymd_opt(2022, 1, 7).unwrap(),
+            commodity: commodity.to_string(),
+            amount: "84.8".parse().unwrap(),
+            currency: currency.to_string(),
+        };
+        let style = AmountStyle {
+            currency_before: Some(currency_before),
+            ..AmountStyle::default()
+        };
+        price_body(&price, &style)
+    }
+
+    #[test]
+    fn symbols_before_the_amount_are_not_spaced_but_words_are() {
+        assert_eq!(body("MSCI", "$", true), "MSCI $84.8");
+        assert_eq!(body("MSCI", "EUR", true), "MSCI EUR 84.8");
+        assert_eq!(body("MSCI", "€", false), "MSCI 84.8 €");
+    }
+
+    #[test]
+    fn currencies_starting_with_e_are_always_spaced_after_the_amount() {
+        let price = MarketPrice {
+            date: NaiveDate::from_ymd_opt(2022, 1, 7).unwrap(),
+            commodity: "MSCI".to_string(),
+            amount: Decimal::from(84),
+            currency: "EUR".to_string(),
+        };
+        let style = AmountStyle {
+            currency_spaced: Some(false),
+            ..AmountStyle::default()
+        };
+        assert_eq!(price_body(&price, &style), "MSCI 84 EUR");
+        let price = MarketPrice {
+            currency: "USD".to_string(),
+            ..price
+        };
+        assert_eq!(price_body(&price, &style), "MSCI 84USD");
+    }
+
+    #[test]
+    fn names_hledger_cant_read_are_quoted() {
+        assert_eq!(quote_commodity("MSCI"), "MSCI");
+        assert_eq!(quote_commodity("VWCE2"), "\"VWCE2\"");
+        assert_eq!(quote_commodity("MSCI WRLD"), "\"MSCI WRLD\"");
+        assert_eq!(quote_commodity("\"MSCI WRLD\""), "\"MSCI WRLD\"");
+        assert_eq!(body("VWCE2", "EUR 1", true), "\"VWCE2\" \"EUR 1\" 84.8");
+    }
+
+    #[test]
+    fn names_with_inner_quotes_are_rejected() {
+        let format = JournalFormat::Hledger;
+        assert!(format.validate_commodity("\"MSCI WRLD\"").is_ok());
+        assert!(matches!(
+            format.validate_commodity("a\"b"),
+            Err(Error::InvalidCommodity { .. })
+        ));
+        assert!(format.validate_commodity("\"").is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! Date ranges and the hledger period expressions describing them.
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+
+/// A range of days that includes `begin` and excludes `end`, like hledger's `--begin` and
+/// `--end` options. A missing bound leaves the range open on that side.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateRange {
+    pub begin: Option<NaiveDate>,
+    pub end: Option<NaiveDate>,
+}
+
+impl DateRange {
+    #[must_use]
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.begin.is_none_or(|begin| date >= begin) && self.end.is_none_or(|end| date < end)
+    }
+
+    /// Narrows the range so that it only contains days after `date`.
+    #[must_use]
+    pub fn after(self, date: NaiveDate) -> Self {
+        let begin = date.succ_opt().unwrap_or(date);
+        Self {
+            begin: Some(self.begin.map_or(begin, |current| current.max(begin))),
+            end: self.end,
+        }
+    }
+}
+
+/// Returns the first day of the quarter containing `date`.
+fn quarter_start(date: NaiveDate) -> NaiveDate {
+    NaiveDate::from_ymd_opt(date.year(), (date.month0() / 3) * 3 + 1, 1).unwrap_or(date)
+}
+
+/// Parses a date that may be given partially (`2022`, `2022-03`, `2022/3/7`) or relative
+/// to `today` (`yesterday`, `lastmonth`, `this year`), returning the first day of the
+/// denoted period and the first day after it.
+fn parse_smart_period(text: &str, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    let keyword: String = text
+        .chars()
+        .filter(|character| !character.is_whitespace())
+        .collect::<String>()
+        .to_lowercase();
+
+    let day = |date: NaiveDate| Some((date, date.succ_opt()?));
+    match keyword.as_str() {
+        "today" => return day(today),
+        "yesterday" => return day(today.pred_opt()?),
+        "tomorrow" => return day(today.succ_opt()?),
+        _ => {}
+    }
+
+    for (prefix, offset) in [("this", 0), ("last", -1), ("next", 1)] {
+        let Some(unit) = keyword.strip_prefix(prefix) else {
+            continue;
+        };
+        let (start, length): (NaiveDate, Length) = match unit {
+            "day" => (today, Days::new(1).into()),
+            "week" => (
+                today - Days::new(u64::from(today.weekday().num_days_from_monday())),
+                Days::new(7).into(),
+            ),
+            "month" => (today.with_day(1)?, Months::new(1).into()),
+            "quarter" => (quarter_start(today), Months::new(3).into()),
+            "year" => (today.with_ordinal(1)?, Months::new(12).into()),
+            _ => return None,
+        };
+        let start = match offset {
+            -1 => length.subtract(start)?,
+            1 => length.add(start)?,
+            _ => start,
+        };
+        return Some((start, length.add(start)?));
+    }
+
+    let parts: Vec<u32> = text
+        .split(['-', '/', '.'])
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match parts[..] {
+        [year] if text.trim().len() == 4 => {
+            let start = NaiveDate::from_ymd_opt(i32::try_from(year).ok()?, 1, 1)?;
+            Some((start, start.checked_add_months(Months::new(12))?))
+        }
+        [year, month] => {
+            let start = NaiveDate::from_ymd_opt(i32::try_from(year).ok()?, month, 1)?;
+            Some((start, start.checked_add_months(Months::new(1))?))
+        }
+        [year, month, day_of_month] => day(NaiveDate::from_ymd_opt(
+            i32::try_from(year).ok()?,
+            month,
+            day_of_month,
+        )?),
+        _ => None,
+    }
+}
+
+/// A length of time that can be added to or subtracted from a date.
+enum Length {
+    Days(Days),
+    Months(Months),
+}
+
+impl From<Days> for Length {
+    fn from(days: Days) -> Self {
+        Self::Days(days)
+    }
+}
+
+impl From<Months> for Length {
+    fn from(months: Months) -> Self {
+        Self::Months(months)
+    }
+}
+
+impl Length {
+    const fn add(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match *self {
+            Self::Days(days) => date.checked_add_days(days),
+            Self::Months(months) => date.checked_add_months(months),
+        }
+    }
+
+    const fn subtract(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match *self {
+            Self::Days(days) => date.checked_sub_days(days),
+            Self::Months(months) => date.checked_sub_months(months),
+        }
+    }
+}
+
+/// Parses a date like hledger's `--begin` and `--end` options do, i.e. partial and
+/// relative dates are accepted and denote the first day of their period.
+///
+/// # Errors
+///
+/// Fails if `text` is not a valid date.
+pub fn parse_date(text: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    parse_smart_period(text, today)
+        .map(|(start, _)| start)
+        .ok_or_else(|| format!("invalid date `{text}`"))
+}
+
+/// Parses a hledger period expression such as `2022`, `lastmonth`, `2021-01..2021-06`
+/// or `from 2021-01 to 2021-06`.
+///
+/// Like in hledger, the end of a range is exclusive, so `2021-01..2021-06` covers
+/// January to May.
+///
+/// # Errors
+///
+/// Fails if `expression` is not a valid period expression.
+pub fn parse_period(expression: &str, today: NaiveDate) -> Result<DateRange, String> {
+    let expression = expression.trim();
+    let bound = |text: &str| -> Result<Option<NaiveDate>, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            Ok(None)
+        } else {
+            parse_date(text, today).map(Some)
+        }
+    };
+
+    // Only ASCII characters are lowercased, so that byte offsets into `lowercase` are valid
+    // in `expression` as well.
+    let lowercase = expression.to_ascii_lowercase();
+    let (begin, end) = if let Some((begin, end)) = expression.split_once("..") {
+        (begin, end)
+    } else if let Some(rest) = ["from ", "since "]
+        .iter()
+        .find_map(|prefix| lowercase.strip_prefix(prefix))
+    {
+        let rest = &expression[expression.len() - rest.len()..];
+        let rest_lowercase = rest.to_ascii_lowercase();
+        match [" to ", " until "].iter().find_map(|separator| {
+            rest_lowercase
+                .find(separator)
+                .map(|index| (index, separator.len()))
+        }) {
+            Some((index, length)) => (&rest[..index], &rest[index + length..]),
+            None => (rest, ""),
+        }
+    } else if let Some(rest) = ["to ", "until "]
+        .iter()
+        .find_map(|prefix| lowercase.strip_prefix(prefix))
+    {
+        ("", &expression[expression.len() - rest.len()..])
+    } else {
+        let (begin, end) = parse_smart_period(expression, today)
+            .ok_or_else(|| format!("invalid period expression `{expression}`"))?;
+        return Ok(DateRange {
+            begin: Some(begin),
+            end: Some(end),
+        });
+    };
+
+    Ok(DateRange {
+        begin: bound(begin)?,
+        end: bound(end)?,
+    })
+}
+
+/// Parses a duration like `30 days`, `4 weeks`, `6 months` or `1y` and returns the
+/// range from that long before `today` until now.
+///
+/// # Errors
+///
+/// Fails if `text` is not a valid duration.
+pub fn parse_last(text: &str, today: NaiveDate) -> Result<DateRange, String> {
+    let error = || format!("invalid duration `{text}`, expected e.g. `30 days` or `4 weeks`");
+    let text = text.trim();
+    let unit_start = text
+        .find(|character: char| !character.is_ascii_digit())
+        .unwrap_or(text.len());
+    let count: u32 = text[..unit_start].parse().map_err(|_| error())?;
+
+    let length: Length = match text[unit_start..].trim().to_lowercase().as_str() {
+        "d" | "day" | "days" => Days::new(u64::from(count)).into(),
+        "w" | "week" | "weeks" => Days::new(u64::from(count) * 7).into(),
+        "m" | "month" | "months" => Months::new(count).into(),
+        "y" | "year" | "years" => Months::new(count.checked_mul(12).ok_or_else(error)?).into(),
+        _ => return Err(error()),
+    };
+
+    Ok(DateRange {
+        begin: Some(length.subtract(today).ok_or_else(error)?),
+        end: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn range(begin: NaiveDate, end: NaiveDate) -> DateRange {
+        DateRange {
+            begin: Some(begin),
+            end: Some(end),
+        }
+    }
+
+    fn today() -> NaiveDate {
+        date(2022, 3, 15)
+    }
+
+    #[test]
+    fn year_covers_the_whole_year() {
+        assert_eq!(
+            parse_period("2022", today()),
+            Ok(range(date(2022, 1, 1), date(2023, 1, 1)))
+        );
+    }
+
+    #[test]
+    fn last_month_is_relative_to_today() {
+        assert_eq!(
+            parse_period("lastmonth", today()),
+            Ok(range(date(2022, 2, 1), date(2022, 3, 1)))
+        );
+    }
+
+    #[test]
+    fn dot_dot_range_has_an_exclusive_end() {
+        assert_eq!(
+            parse_period("2021-01..2021-06", today()),
+            Ok(range(date(2021, 1, 1), date(2021, 6, 1)))
+        );
+    }
+
+    #[test]
+    fn from_to_range_ignores_case() {
+        assert_eq!(
+            parse_period("From 2021/01/15 TO 2021-06", today()),
+            Ok(range(date(2021, 1, 15), date(2021, 6, 1)))
+        );
+    }
+
+    #[test]
+    fn non_ascii_dates_are_reported_whole() {
+        assert_eq!(
+            parse_period("from İİ to 2022", today()),
+            Err("invalid date `İİ`".to_string())
+        );
+    }
+
+    #[test]
+    fn overflowing_durations_are_invalid() {
+        assert!(parse_last("400000000y", today()).is_err());
+        assert_eq!(
+            parse_last("2 weeks", today()),
+            Ok(DateRange {
+                begin: Some(date(2022, 3, 1)),
+                end: None,
+            })
+        );
+    }
+}
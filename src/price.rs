@@ -0,0 +1,140 @@
+//! Market prices as returned by the library functions.
+
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+
+use crate::provider::Quote;
+
+/// Number of decimal digits averages keep beyond the most precise of the averaged prices.
+const AVERAGE_EXTRA_DIGITS: u32 = 4;
+
+/// The price of one unit of `commodity`, denoted in `currency`, on `date`.
+///
+/// This corresponds to a single hledger `P` directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarketPrice {
+    pub date: chrono::NaiveDate,
+    pub commodity: String,
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+/// Which of the prices of a trading day is used as its market price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PriceField {
+    Open,
+    High,
+    Low,
+    #[default]
+    Close,
+    /// Close price adjusted for splits and distributions
+    AdjustedClose,
+    /// Average of high, low and close price
+    Typical,
+    /// Average of high and low price
+    Midpoint,
+}
+
+impl PriceField {
+    /// Names accepted by [`PriceField::from_str`].
+    pub const NAMES: &'static [&'static str] = &[
+        "open",
+        "high",
+        "low",
+        "close",
+        "adjusted-close",
+        "typical",
+        "midpoint",
+    ];
+
+    /// Whether the provider has to be asked for prices adjusted for splits and distributions.
+    #[must_use]
+    pub const fn needs_adjusted_prices(self) -> bool {
+        matches!(self, Self::AdjustedClose)
+    }
+
+    /// Returns this price of `price`, or `None` if the provider didn't supply it.
+    #[must_use]
+    pub fn select(self, price: &Quote) -> Option<Decimal> {
+        match self {
+            Self::Open => Some(price.open),
+            Self::High => Some(price.high),
+            Self::Low => Some(price.low),
+            Self::Close => Some(price.close),
+            Self::AdjustedClose => price.adjusted_close,
+            Self::Typical => Some(mean(&[price.high, price.low, price.close])),
+            Self::Midpoint => Some(mean(&[price.high, price.low])),
+        }
+    }
+}
+
+/// Returns the average of `prices`, or zero if there are none.
+///
+/// The average is rounded to a few more decimal digits than the most precise price has,
+/// instead of the 28 digits a division like `10 / 3` results in.
+#[must_use]
+pub fn mean(prices: &[Decimal]) -> Decimal {
+    let Some(scale) = prices.iter().map(Decimal::scale).max() else {
+        return Decimal::ZERO;
+    };
+    let sum: Decimal = prices.iter().sum();
+    (sum / Decimal::from(prices.len())).round_dp(scale + AVERAGE_EXTRA_DIGITS)
+}
+
+impl FromStr for PriceField {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "open" => Ok(Self::Open),
+            "high" => Ok(Self::High),
+            "low" => Ok(Self::Low),
+            "close" => Ok(Self::Close),
+            "adjusted-close" => Ok(Self::AdjustedClose),
+            "typical" => Ok(Self::Typical),
+            "midpoint" => Ok(Self::Midpoint),
+            _ => Err(format!(
+                "unknown price field `{}`, expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(high: &str, low: &str, close: &str) -> Quote {
+        Quote {
+            date: chrono::NaiveDate::from_ymd_opt(2022, 1, 7).unwrap(),
+            open: close.parse().unwrap(),
+            high: high.parse().unwrap(),
+            low: low.parse().unwrap(),
+            close: close.parse().unwrap(),
+            adjusted_close: None,
+            volume: None,
+        }
+    }
+
+    #[test]
+    fn averages_are_rounded_to_a_few_more_digits() {
+        let price = quote("1258.50", "1256.00", "1258.425");
+        assert_eq!(
+            PriceField::Typical.select(&price),
+            Some("1257.6416667".parse().unwrap())
+        );
+        assert_eq!(
+            PriceField::Midpoint.select(&price),
+            Some("1257.25".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn mean_of_nothing_is_zero() {
+        assert_eq!(mean(&[]), Decimal::ZERO);
+    }
+}
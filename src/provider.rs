@@ -0,0 +1,164 @@
+//! Sources that market prices can be fetched from.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rust_decimal::Decimal;
+
+use crate::asset::Asset;
+use crate::cache::Cache;
+use crate::rate_limit::RateLimiter;
+use crate::Result;
+
+mod alpha_vantage;
+
+pub use self::alpha_vantage::AlphaVantage;
+
+/// A listing found by [`PriceProvider::search`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SymbolMatch {
+    pub symbol: String,
+    pub name: String,
+    /// Kind of security, e.g. `Equity` or `ETF`
+    #[serde(rename = "type")]
+    pub asset_type: String,
+    pub region: String,
+    /// ISO code of the currency the listing is traded in
+    pub currency: String,
+    /// Time the exchange opens, e.g. `09:30`, in its time zone
+    pub market_open: String,
+    /// Time the exchange closes, e.g. `16:00`, in its time zone
+    pub market_close: String,
+    /// Time zone of the exchange, e.g. `UTC-04`
+    pub timezone: String,
+    /// How well the listing matches the query, from 0 to 1
+    pub match_score: f64,
+}
+
+/// The prices of a symbol during an [`Interval`].
+#[derive(Debug, Clone)]
+pub struct Quote {
+    /// The last trading day of the interval
+    pub date: chrono::NaiveDate,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    /// Close price adjusted for splits and distributions, if requested
+    pub adjusted_close: Option<Decimal>,
+    /// Traded volume, if the provider knows it
+    pub volume: Option<f64>,
+}
+
+impl Quote {
+    /// Applies `function` to all prices, e.g. to convert them into another currency.
+    #[must_use]
+    pub fn map_prices(self, function: impl Fn(Decimal) -> Decimal) -> Self {
+        Self {
+            open: function(self.open),
+            high: function(self.high),
+            low: function(self.low),
+            close: function(self.close),
+            adjusted_close: self.adjusted_close.map(&function),
+            ..self
+        }
+    }
+}
+
+/// A service that can look up symbols and return their historic prices.
+#[async_trait::async_trait]
+pub trait PriceProvider: Send + Sync {
+    /// Searches for listings matching `query`.
+    async fn search(&self, query: &str) -> Result<Vec<SymbolMatch>>;
+
+    /// Returns the prices of `asset` in no particular order.
+    ///
+    /// Providers that don't support the kind of asset fail with [`crate::Error::Unsupported`].
+    async fn history(&self, asset: &Asset, query: &HistoryQuery) -> Result<Vec<Quote>>;
+
+    /// Returns the symbol this provider uses for the listing with `ticker` on the exchange
+    /// with the Bloomberg exchange code `exchange_code`, e.g. `XDWD.DEX` for `XDWD` on `GY`.
+    ///
+    /// Returns `None` if the provider doesn't cover the exchange.
+    fn listing_symbol(&self, ticker: &str, exchange_code: &str) -> Option<String>;
+}
+
+/// The length of time a [`Quote`] covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interval {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// Which prices [`PriceProvider::history`] has to return.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HistoryQuery {
+    /// Only prices from this day on are needed. Providers may use this to request less
+    /// data, but are free to return older prices as well.
+    pub since: Option<chrono::NaiveDate>,
+    /// Whether [`Quote::adjusted_close`] has to be filled in
+    pub adjusted: bool,
+    pub interval: Interval,
+}
+
+/// Settings shared by all providers created during a run.
+#[derive(Debug, Clone)]
+pub struct ProviderSettings {
+    /// Limiter all requests have to pass
+    pub rate_limiter: Arc<RateLimiter>,
+    /// How often a request that failed temporarily (e.g. because of a rate limit) is retried
+    pub max_retries: u32,
+    /// Cache for responses, or `None` to always fetch them
+    pub cache: Option<Cache>,
+}
+
+impl Default for ProviderSettings {
+    fn default() -> Self {
+        Self {
+            rate_limiter: Arc::default(),
+            max_retries: 3,
+            cache: None,
+        }
+    }
+}
+
+/// The price providers that can be selected on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProviderKind {
+    #[default]
+    AlphaVantage,
+}
+
+impl ProviderKind {
+    /// Names accepted by [`ProviderKind::from_str`].
+    pub const NAMES: &'static [&'static str] = &["alpha-vantage"];
+
+    /// Creates a provider of this kind, reading its credentials from the environment.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the credentials are missing.
+    pub fn create(self, settings: &ProviderSettings) -> Result<Box<dyn PriceProvider>> {
+        Ok(match self {
+            Self::AlphaVantage => Box::new(AlphaVantage::from_env(settings.clone())?),
+        })
+    }
+}
+
+impl FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name {
+            "alpha-vantage" => Ok(Self::AlphaVantage),
+            _ => Err(format!(
+                "unknown provider `{}`, expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
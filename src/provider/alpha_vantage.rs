@@ -0,0 +1,518 @@
+//! [`PriceProvider`] implementation for the Alpha Vantage API.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use rust_decimal::Decimal;
+
+use super::{HistoryQuery, Interval, PriceProvider, ProviderSettings, Quote, SymbolMatch};
+use crate::asset::Asset;
+use crate::cache::{Cache, CacheMode};
+use crate::rate_limit::{with_retries, RateLimiter};
+use crate::{Error, Result};
+
+const API_KEY_VARIABLE: &str = "HLEDGER_GET_MARKET_PRICES_API_KEY";
+
+/// Bloomberg codes of the exchanges Alpha Vantage covers and the suffix it appends to
+/// ticker symbols of listings on them.
+const EXCHANGE_SUFFIXES: &[(&str, &str)] = &[
+    ("US", ""),
+    ("UN", ""),
+    ("UW", ""),
+    ("UQ", ""),
+    ("UA", ""),
+    ("UR", ""),
+    ("UP", ""),
+    ("LN", ".LON"),
+    ("GY", ".DEX"),
+    ("GF", ".FRK"),
+    ("CT", ".TRT"),
+    ("CV", ".TRV"),
+    ("IB", ".BSE"),
+    ("CG", ".SHH"),
+    ("CS", ".SHZ"),
+    ("BS", ".SAO"),
+];
+
+/// Compact responses contain the latest 100 trading days, which always cover at least
+/// this many calendar days.
+const COMPACT_OUTPUT_DAYS: i64 = 100;
+
+/// Fetches prices from <https://www.alphavantage.co>.
+pub struct AlphaVantage {
+    client: ::alpha_vantage::api::ApiClient,
+    settings: ProviderSettings,
+}
+
+impl AlphaVantage {
+    /// Creates a client using the API key in `HLEDGER_GET_MARKET_PRICES_API_KEY`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the variable is not set or not valid Unicode. In offline mode, no API key
+    /// is needed.
+    pub fn from_env(settings: ProviderSettings) -> Result<Self> {
+        let offline = settings
+            .cache
+            .as_ref()
+            .is_some_and(|cache| cache.mode() == CacheMode::Offline);
+        let api_key = match std::env::var(API_KEY_VARIABLE) {
+            Ok(api_key) => api_key,
+            Err(_) if offline => String::new(),
+            Err(std::env::VarError::NotPresent) => {
+                return Err(Error::MissingApiKey {
+                    variable: API_KEY_VARIABLE,
+                })
+            }
+            Err(std::env::VarError::NotUnicode(_)) => {
+                return Err(Error::InvalidApiKey(format!(
+                    "environment variable {API_KEY_VARIABLE} is not valid Unicode"
+                )))
+            }
+        };
+
+        let http_client = CachingClient {
+            client: crate::build_http_client()?,
+            cache: settings.cache.clone(),
+            rate_limiter: Arc::clone(&settings.rate_limiter),
+        };
+
+        Ok(Self {
+            client: ::alpha_vantage::set_api(&api_key, http_client),
+            settings,
+        })
+    }
+
+    /// Sends requests, retrying them if the per-minute limit was hit or the network failed.
+    async fn request<T, Fut>(&self, mut request: impl FnMut() -> Fut) -> Result<T>
+    where
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        if let Some(cache) = self
+            .settings
+            .cache
+            .as_ref()
+            .filter(|cache| cache.mode() == CacheMode::Offline)
+        {
+            return request().await.map_err(|error| match error {
+                Error::Network(_) => Error::NotCached(format!(
+                    "no cached Alpha Vantage response found in {}",
+                    cache.directory().display()
+                )),
+                error => error,
+            });
+        }
+
+        with_retries(
+            self.settings.rate_limiter.request_interval(),
+            self.settings.max_retries,
+            |error| match error {
+                // Retrying won't help once the daily limit is used up.
+                Error::RateLimited(message) => {
+                    message.contains("per minute") || !message.contains("per day")
+                }
+                Error::Network(_) => true,
+                _ => false,
+            },
+            request,
+        )
+        .await
+    }
+
+    async fn stock_history(
+        &self,
+        symbol: &str,
+        query: &HistoryQuery,
+        output_size: Option<&str>,
+    ) -> Result<Vec<Quote>> {
+        let function = match (query.interval, query.adjusted) {
+            (Interval::Daily, false) => "TIME_SERIES_DAILY",
+            (Interval::Daily, true) => "TIME_SERIES_DAILY_ADJUSTED",
+            (Interval::Weekly, false) => "TIME_SERIES_WEEKLY",
+            (Interval::Weekly, true) => "TIME_SERIES_WEEKLY_ADJUSTED",
+            (Interval::Monthly, false) => "TIME_SERIES_MONTHLY",
+            (Interval::Monthly, true) => "TIME_SERIES_MONTHLY_ADJUSTED",
+        };
+        let mut parameters = vec![("symbol", symbol)];
+        parameters.extend(output_size.map(|output_size| ("outputsize", output_size)));
+
+        let series = self.time_series(function, &parameters, symbol).await?;
+        series
+            .iter()
+            .map(|(date, entry)| {
+                let volume_field = if query.adjusted {
+                    "6. volume"
+                } else {
+                    "5. volume"
+                };
+                Ok(Quote {
+                    date: parse_date(date)?,
+                    open: price(entry, date, &["1. open"])?,
+                    high: price(entry, date, &["2. high"])?,
+                    low: price(entry, date, &["3. low"])?,
+                    close: price(entry, date, &["4. close"])?,
+                    adjusted_close: query
+                        .adjusted
+                        .then(|| price(entry, date, &["5. adjusted close"]))
+                        .transpose()?,
+                    volume: volume(entry, volume_field),
+                })
+            })
+            .collect()
+    }
+
+    async fn fx_history(
+        &self,
+        from: &str,
+        to: &str,
+        query: &HistoryQuery,
+        output_size: Option<&str>,
+    ) -> Result<Vec<Quote>> {
+        if query.adjusted {
+            return Err(Error::Unsupported(
+                "Alpha Vantage has no adjusted exchange rates".to_string(),
+            ));
+        }
+        let function = match query.interval {
+            Interval::Daily => "FX_DAILY",
+            Interval::Weekly => "FX_WEEKLY",
+            Interval::Monthly => "FX_MONTHLY",
+        };
+        let mut parameters = vec![("from_symbol", from), ("to_symbol", to)];
+        parameters.extend(output_size.map(|output_size| ("outputsize", output_size)));
+
+        let series = self
+            .time_series(function, &parameters, &format!("{from}/{to}"))
+            .await?;
+        series
+            .iter()
+            .map(|(date, entry)| {
+                Ok(Quote {
+                    date: parse_date(date)?,
+                    open: price(entry, date, &["1. open"])?,
+                    high: price(entry, date, &["2. high"])?,
+                    low: price(entry, date, &["3. low"])?,
+                    close: price(entry, date, &["4. close"])?,
+                    adjusted_close: None,
+                    volume: None,
+                })
+            })
+            .collect()
+    }
+
+    async fn crypto_history(
+        &self,
+        symbol: &str,
+        market: &str,
+        query: &HistoryQuery,
+    ) -> Result<Vec<Quote>> {
+        if query.adjusted {
+            return Err(Error::Unsupported(
+                "Alpha Vantage has no adjusted cryptocurrency prices".to_string(),
+            ));
+        }
+        let function = match query.interval {
+            Interval::Daily => "DIGITAL_CURRENCY_DAILY",
+            Interval::Weekly => "DIGITAL_CURRENCY_WEEKLY",
+            Interval::Monthly => "DIGITAL_CURRENCY_MONTHLY",
+        };
+        let parameters = [("symbol", symbol), ("market", market)];
+
+        let series = self
+            .time_series(function, &parameters, &format!("{symbol}/{market}"))
+            .await?;
+        // Older responses contain the prices in both the market currency and US dollars,
+        // e.g. `1a. open (EUR)` and `1b. open (USD)`, newer ones only in the market currency.
+        let field = |number: &str, name: &str| {
+            [
+                format!("{number}a. {name} ({market})"),
+                format!("{number}. {name}"),
+            ]
+        };
+        let fields = [
+            field("1", "open"),
+            field("2", "high"),
+            field("3", "low"),
+            field("4", "close"),
+        ];
+        series
+            .iter()
+            .map(|(date, entry)| {
+                Ok(Quote {
+                    date: parse_date(date)?,
+                    open: price(entry, date, &fields[0])?,
+                    high: price(entry, date, &fields[1])?,
+                    low: price(entry, date, &fields[2])?,
+                    close: price(entry, date, &fields[3])?,
+                    adjusted_close: None,
+                    volume: volume(entry, "5. volume"),
+                })
+            })
+            .collect()
+    }
+
+    /// Requests a daily, weekly or monthly series and returns its entries by date, leaving
+    /// the prices as the decimal numbers the response contains.
+    ///
+    /// The `alpha_vantage` crate would parse them as `f64`, which can't represent most
+    /// decimal prices exactly.
+    async fn time_series(
+        &self,
+        function: &str,
+        parameters: &[(&str, &str)],
+        name: &str,
+    ) -> Result<BTreeMap<String, Entry>> {
+        let response: HashMap<String, serde_json::Value> = self
+            .request(|| async {
+                let mut builder = self.client.custom(function);
+                for (key, value) in parameters {
+                    builder.extra_params(key, value);
+                }
+                builder
+                    .json()
+                    .await
+                    .map_err(|error| convert_error(error, name))
+            })
+            .await?;
+
+        // The series is named after the function, e.g. `Weekly Adjusted Time Series` or
+        // `Time Series FX (Daily)`.
+        let series = response
+            .into_iter()
+            .find_map(|(key, series)| key.contains("Time Series").then_some(series))
+            .ok_or_else(|| {
+                Error::MalformedResponse(format!("the response for {name} contains no prices"))
+            })?;
+        serde_json::from_value(series).map_err(|error| Error::MalformedResponse(error.to_string()))
+    }
+}
+
+/// HTTP client serving responses from the cache where possible and sending all other
+/// requests through the rate limiter.
+struct CachingClient {
+    client: reqwest::Client,
+    cache: Option<Cache>,
+    rate_limiter: Arc<RateLimiter>,
+}
+
+/// Returns the cache key for `url`, consisting of the values of all query parameters
+/// except the API key, e.g. `["alpha-vantage", "TIME_SERIES_DAILY", "IBM", "full"]`.
+fn cache_key(url: &str) -> Option<Vec<String>> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let mut key = vec!["alpha-vantage".to_string()];
+    key.extend(
+        url.query_pairs()
+            .filter(|(name, _)| name != "apikey")
+            .map(|(_, value)| value.into_owned()),
+    );
+    Some(key)
+}
+
+/// Checks whether `response` contains data instead of an error message or a rate limit
+/// notice, which must not be cached.
+fn is_cacheable(response: &str) -> bool {
+    serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(response).is_ok_and(
+        |object| {
+            !["Note", "Information", "Error Message"]
+                .iter()
+                .any(|field| object.contains_key(*field))
+        },
+    )
+}
+
+#[async_trait::async_trait]
+impl ::alpha_vantage::client::HttpClient for CachingClient {
+    async fn get_alpha_vantage_provider_output(
+        &self,
+        path: String,
+    ) -> ::alpha_vantage::error::Result<String> {
+        let key = cache_key(&path);
+        let key: Option<Vec<&str>> = key
+            .as_ref()
+            .map(|key| key.iter().map(String::as_str).collect());
+
+        if let (Some(cache), Some(key)) = (&self.cache, &key) {
+            // A full response contains everything a compact one would.
+            let full_key: Vec<&str> = key
+                .iter()
+                .map(|component| match *component {
+                    "compact" => "full",
+                    component => component,
+                })
+                .collect();
+            if let Some(response) = cache.get(key).or_else(|| cache.get(&full_key)) {
+                return Ok(response);
+            }
+            if cache.mode() == CacheMode::Offline {
+                return Err(::alpha_vantage::error::Error::GetRequestFailed);
+            }
+        }
+
+        self.rate_limiter.acquire().await;
+        let response = self.client.get_alpha_vantage_provider_output(path).await?;
+
+        if let (Some(cache), Some(key)) = (&self.cache, &key) {
+            if is_cacheable(&response) {
+                cache.put(key, &response);
+            }
+        }
+        Ok(response)
+    }
+
+    async fn get_rapid_api_provider_output(
+        &self,
+        path: String,
+        api_key: String,
+    ) -> ::alpha_vantage::error::Result<String> {
+        self.rate_limiter.acquire().await;
+        self.client
+            .get_rapid_api_provider_output(path, api_key)
+            .await
+    }
+}
+
+/// Translates an error of the `alpha_vantage` crate, which reports most problems as
+/// free-form messages from the API.
+fn convert_error(error: ::alpha_vantage::error::Error, symbol: &str) -> Error {
+    use ::alpha_vantage::error::Error as AlphaVantageError;
+
+    match error {
+        AlphaVantageError::AlphaVantageInformation(message)
+        | AlphaVantageError::AlphaVantageErrorMessage(message)
+        | AlphaVantageError::AlphaVantageNote(message)
+            if message.contains("apikey") =>
+        {
+            Error::InvalidApiKey(message)
+        }
+        AlphaVantageError::AlphaVantageNote(message) => Error::RateLimited(message),
+        AlphaVantageError::AlphaVantageInformation(message)
+            if message.contains("rate limit") || message.contains("call frequency") =>
+        {
+            Error::RateLimited(message)
+        }
+        AlphaVantageError::AlphaVantageErrorMessage(message) => Error::UnknownSymbol {
+            symbol: symbol.to_string(),
+            message,
+        },
+        AlphaVantageError::AlphaVantageInformation(message) => Error::MalformedResponse(message),
+        AlphaVantageError::GetRequestFailed => {
+            Error::Network("could not get a response from Alpha Vantage".to_string())
+        }
+        AlphaVantageError::EmptyResponse | AlphaVantageError::DecodeJsonToStruct => {
+            Error::MalformedResponse(error.to_string())
+        }
+        AlphaVantageError::DesiredNumberOfEntryNotPresent(_) => Error::Internal(error.to_string()),
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceProvider for AlphaVantage {
+    async fn search(&self, query: &str) -> Result<Vec<SymbolMatch>> {
+        let search = self
+            .request(|| async {
+                self.client
+                    .search(query)
+                    .json()
+                    .await
+                    .map_err(|error| convert_error(error, query))
+            })
+            .await?;
+
+        Ok(search
+            .result()
+            .iter()
+            .map(|result| SymbolMatch {
+                symbol: result.symbol().to_string(),
+                name: result.name().to_string(),
+                asset_type: result.stock_type().to_string(),
+                region: result.region().to_string(),
+                currency: result.currency().to_string(),
+                market_open: result.market_open().to_string(),
+                market_close: result.market_close().to_string(),
+                timezone: result.time_zone().to_string(),
+                match_score: result.match_score(),
+            })
+            .collect())
+    }
+
+    async fn history(&self, asset: &Asset, query: &HistoryQuery) -> Result<Vec<Quote>> {
+        // Only daily series can be shortened, weekly and monthly ones are always complete.
+        let today = chrono::Local::now().date_naive();
+        let output_size = match (query.interval, query.since) {
+            (Interval::Daily, Some(since)) if (today - since).num_days() < COMPACT_OUTPUT_DAYS => {
+                Some("compact")
+            }
+            (Interval::Daily, _) => Some("full"),
+            _ => None,
+        };
+
+        match asset {
+            Asset::Security(symbol) => self.stock_history(symbol, query, output_size).await,
+            Asset::Currency { from, to } => self.fx_history(from, to, query, output_size).await,
+            Asset::Crypto { symbol, market } => self.crypto_history(symbol, market, query).await,
+        }
+    }
+
+    fn listing_symbol(&self, ticker: &str, exchange_code: &str) -> Option<String> {
+        // Share classes are separated by a slash on Bloomberg, e.g. `BRK/B`, but by a dash
+        // on Alpha Vantage.
+        let ticker = ticker.replace('/', "-");
+        EXCHANGE_SUFFIXES
+            .iter()
+            .find(|(code, _)| *code == exchange_code)
+            .map(|(_, suffix)| format!("{ticker}{suffix}"))
+    }
+}
+
+/// The prices of one day, week or month, keyed by field names like `4. close`.
+type Entry = HashMap<String, String>;
+
+/// Parses the price in the first of the fields `names` that `entry` contains.
+///
+/// Trailing zeros are removed, as Alpha Vantage pads all prices to the same number of
+/// decimal digits.
+fn price(entry: &Entry, date: &str, names: &[impl AsRef<str>]) -> Result<Decimal> {
+    let text = names
+        .iter()
+        .find_map(|name| entry.get(name.as_ref()))
+        .ok_or_else(|| {
+            let name = names.first().map_or("", AsRef::as_ref);
+            Error::MalformedResponse(format!("the entry of {date} has no field `{name}`"))
+        })?;
+    text.parse::<Decimal>()
+        .map(|price| price.normalize())
+        .map_err(|error| Error::MalformedResponse(format!("invalid price `{text}`: {error}")))
+}
+
+/// Parses the volume in the field `name`, if there is a valid one.
+fn volume(entry: &Entry, name: &str) -> Option<f64> {
+    entry.get(name).and_then(|volume| volume.parse().ok())
+}
+
+/// Parses the date of a daily, weekly or monthly entry.
+fn parse_date(time: &str) -> Result<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(time, "%Y-%m-%d")
+        .map_err(|error| Error::MalformedResponse(format!("invalid date `{time}`: {error}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices_are_read_exactly() {
+        let entry: Entry = [
+            ("1a. open (EUR)", "60000.00000000"),
+            ("4. close", "1257.6400000000000001"),
+        ]
+        .into_iter()
+        .map(|(name, price)| (name.to_string(), price.to_string()))
+        .collect();
+
+        let open = price(&entry, "2022-01-07", &["1a. open (EUR)", "1. open"]).unwrap();
+        assert_eq!(open.to_string(), "60000");
+        let close = price(&entry, "2022-01-07", &["4. close"]).unwrap();
+        assert_eq!(close.to_string(), "1257.6400000000000001");
+        assert!(price(&entry, "2022-01-07", &["2. high"]).is_err());
+    }
+}
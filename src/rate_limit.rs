@@ -0,0 +1,116 @@
+//! Throttling and retrying of requests to providers with usage limits.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+use crate::Result;
+
+/// A token bucket allowing a burst of requests, refilled at a steady rate.
+///
+/// Share a single limiter between everything that talks to the same service.
+#[derive(Debug)]
+pub struct RateLimiter {
+    bucket: Option<Mutex<Bucket>>,
+    capacity: f64,
+    tokens_per_second: f64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `requests_per_minute` requests, or any number of requests
+    /// if it is zero.
+    #[must_use]
+    pub fn new(requests_per_minute: u32) -> Self {
+        let capacity = f64::from(requests_per_minute);
+        Self {
+            bucket: (requests_per_minute > 0).then(|| {
+                Mutex::new(Bucket {
+                    tokens: capacity,
+                    last_refill: Instant::now(),
+                })
+            }),
+            capacity,
+            tokens_per_second: capacity / 60.0,
+        }
+    }
+
+    /// Waits until another request may be made.
+    pub async fn acquire(&self) {
+        let Some(bucket) = &self.bucket else {
+            return;
+        };
+
+        // Holding the lock while sleeping makes waiting requests proceed in order.
+        let mut bucket = bucket.lock().await;
+        self.refill(&mut bucket);
+        if bucket.tokens < 1.0 {
+            let missing = 1.0 - bucket.tokens;
+            tokio::time::sleep(Duration::from_secs_f64(missing / self.tokens_per_second)).await;
+            self.refill(&mut bucket);
+        }
+        bucket.tokens = (bucket.tokens - 1.0).max(0.0);
+    }
+
+    /// Returns the time it takes to regain the allowance for a single request.
+    #[must_use]
+    pub fn request_interval(&self) -> Duration {
+        if self.bucket.is_some() {
+            Duration::from_secs_f64(1.0 / self.tokens_per_second)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = elapsed
+            .mul_add(self.tokens_per_second, bucket.tokens)
+            .min(self.capacity);
+        bucket.last_refill = now;
+    }
+}
+
+impl Default for RateLimiter {
+    /// Allows 5 requests per minute, the limit of the Alpha Vantage free tier.
+    fn default() -> Self {
+        Self::new(5)
+    }
+}
+
+/// Calls `request`, retrying up to `max_retries` times with exponential backoff starting at
+/// `initial_backoff` if it fails with an error that `should_retry` considers temporary.
+///
+/// # Errors
+///
+/// Returns the error of the last attempt.
+pub async fn with_retries<T, F, Fut>(
+    initial_backoff: Duration,
+    max_retries: u32,
+    should_retry: impl Fn(&crate::Error) -> bool,
+    mut request: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut backoff = initial_backoff.max(Duration::from_secs(1));
+    let mut attempt = 0;
+    loop {
+        match request().await {
+            Err(error) if attempt < max_retries && should_retry(&error) => {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Reducing daily prices to one price per week, month, quarter or year.
+
+use std::str::FromStr;
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+use rust_decimal::Decimal;
+
+use crate::price::mean;
+use crate::provider::{Interval, Quote};
+
+/// How many market prices are output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Frequency {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+/// How the prices of a period are combined into one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Aggregation {
+    /// The price of the last trading day in the period
+    #[default]
+    Last,
+    /// The average price of all trading days in the period
+    Average,
+}
+
+impl Frequency {
+    /// Names accepted by [`Frequency::from_str`].
+    pub const NAMES: &'static [&'static str] =
+        &["daily", "weekly", "monthly", "quarterly", "yearly"];
+
+    /// Returns a value identifying the period `date` lies in.
+    fn period(self, date: NaiveDate) -> (i32, u32) {
+        match self {
+            Self::Daily => (date.year(), date.ordinal()),
+            Self::Weekly => {
+                let week = date.iso_week();
+                (week.year(), week.week())
+            }
+            Self::Monthly => (date.year(), date.month()),
+            Self::Quarterly => (date.year(), date.month0() / 3),
+            Self::Yearly => (date.year(), 0),
+        }
+    }
+
+    /// Returns the last day of the period `date` lies in.
+    #[must_use]
+    pub fn last_day(self, date: NaiveDate) -> NaiveDate {
+        let next_period = match self {
+            Self::Daily => date.succ_opt(),
+            Self::Weekly => date.checked_add_days(Days::new(u64::from(
+                7 - date.weekday().num_days_from_monday(),
+            ))),
+            Self::Monthly => date
+                .with_day(1)
+                .and_then(|start| start.checked_add_months(Months::new(1))),
+            Self::Quarterly => NaiveDate::from_ymd_opt(date.year(), date.month0() / 3 * 3 + 1, 1)
+                .and_then(|start| start.checked_add_months(Months::new(3))),
+            Self::Yearly => NaiveDate::from_ymd_opt(date.year() + 1, 1, 1),
+        };
+        next_period
+            .and_then(|next_period| next_period.pred_opt())
+            .unwrap_or(NaiveDate::MAX)
+    }
+
+    /// Returns the coarsest interval a provider can be asked for that still allows
+    /// computing prices of this frequency.
+    #[must_use]
+    pub const fn provider_interval(self) -> Interval {
+        match self {
+            Self::Daily => Interval::Daily,
+            Self::Weekly => Interval::Weekly,
+            Self::Monthly | Self::Quarterly | Self::Yearly => Interval::Monthly,
+        }
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "daily" => Ok(Self::Daily),
+            "weekly" => Ok(Self::Weekly),
+            "monthly" => Ok(Self::Monthly),
+            "quarterly" => Ok(Self::Quarterly),
+            "yearly" => Ok(Self::Yearly),
+            _ => Err(format!(
+                "unknown frequency `{}`, expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl Aggregation {
+    /// Names accepted by [`Aggregation::from_str`].
+    pub const NAMES: &'static [&'static str] = &["last", "average"];
+}
+
+impl FromStr for Aggregation {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "last" => Ok(Self::Last),
+            "average" => Ok(Self::Average),
+            _ => Err(format!(
+                "unknown aggregation `{}`, expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl Aggregation {
+    /// Combines the quotes of a period, sorted newest first, into one.
+    fn combine(self, period: &[Quote]) -> Option<Quote> {
+        let newest = period.first()?.clone();
+        if self == Self::Last {
+            return Some(newest);
+        }
+
+        let average =
+            |price: fn(&Quote) -> Decimal| mean(&period.iter().map(price).collect::<Vec<_>>());
+        let adjusted_close = period
+            .iter()
+            .map(|quote| quote.adjusted_close)
+            .collect::<Option<Vec<_>>>()
+            .map(|prices| mean(&prices));
+        #[allow(clippy::cast_precision_loss)]
+        let volume = period
+            .iter()
+            .map(|quote| quote.volume)
+            .sum::<Option<f64>>()
+            .map(|sum| sum / period.len() as f64);
+        Some(Quote {
+            date: newest.date,
+            open: average(|quote| quote.open),
+            high: average(|quote| quote.high),
+            low: average(|quote| quote.low),
+            close: average(|quote| quote.close),
+            adjusted_close,
+            volume,
+        })
+    }
+}
+
+/// Combines `quotes`, which must be sorted newest first, into one quote per period of
+/// `frequency`. The combined quote is dated on the last trading day of its period.
+///
+/// Periods that haven't ended before `until` are left out, as further prices could still
+/// be added to them. This is usually the end of the requested range or today.
+#[must_use]
+pub fn resample(
+    quotes: Vec<Quote>,
+    frequency: Frequency,
+    aggregation: Aggregation,
+    until: NaiveDate,
+) -> Vec<Quote> {
+    if frequency == Frequency::Daily {
+        return quotes;
+    }
+
+    let mut periods: Vec<Vec<Quote>> = Vec::new();
+    for quote in quotes {
+        if frequency.last_day(quote.date) >= until {
+            continue;
+        }
+        match periods.last_mut() {
+            Some(period) if frequency.period(period[0].date) == frequency.period(quote.date) => {
+                period.push(quote);
+            }
+            _ => periods.push(vec![quote]),
+        }
+    }
+
+    periods
+        .into_iter()
+        .filter_map(|period| aggregation.combine(&period))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn quote(day: &str, close: i64) -> Quote {
+        let close = Decimal::from(close);
+        Quote {
+            date: date(day),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            adjusted_close: None,
+            volume: None,
+        }
+    }
+
+    fn dates(quotes: &[Quote]) -> Vec<NaiveDate> {
+        quotes.iter().map(|quote| quote.date).collect()
+    }
+
+    #[test]
+    fn weeks_are_grouped_by_iso_year() {
+        // 2021-01-01 lies in the last week of 2020, 2024-12-30 in the first week of 2025.
+        let quotes = vec![
+            quote("2025-01-03", 6),
+            quote("2024-12-30", 5),
+            quote("2021-01-04", 4),
+            quote("2021-01-01", 3),
+            quote("2020-12-28", 2),
+        ];
+        let weekly = resample(
+            quotes,
+            Frequency::Weekly,
+            Aggregation::Last,
+            date("2025-02-01"),
+        );
+        assert_eq!(
+            dates(&weekly),
+            [date("2025-01-03"), date("2021-01-04"), date("2021-01-01")]
+        );
+    }
+
+    #[test]
+    fn unfinished_periods_are_left_out() {
+        let quotes = vec![
+            quote("2022-02-01", 3),
+            quote("2022-01-31", 2),
+            quote("2022-01-28", 1),
+        ];
+        let monthly = resample(
+            quotes.clone(),
+            Frequency::Monthly,
+            Aggregation::Average,
+            date("2022-02-15"),
+        );
+        assert_eq!(dates(&monthly), [date("2022-01-31")]);
+        assert_eq!(monthly[0].close, Decimal::new(15, 1));
+
+        // A period ending on the last day before `until` is complete.
+        let monthly = resample(
+            quotes,
+            Frequency::Monthly,
+            Aggregation::Last,
+            date("2022-03-01"),
+        );
+        assert_eq!(dates(&monthly), [date("2022-02-01"), date("2022-01-31")]);
+    }
+
+    #[test]
+    fn last_day_of_periods() {
+        let day = date("2021-11-17");
+        assert_eq!(Frequency::Daily.last_day(day), day);
+        assert_eq!(Frequency::Weekly.last_day(day), date("2021-11-21"));
+        assert_eq!(Frequency::Monthly.last_day(day), date("2021-11-30"));
+        assert_eq!(Frequency::Quarterly.last_day(day), date("2021-12-31"));
+        assert_eq!(Frequency::Yearly.last_day(day), date("2021-12-31"));
+        assert_eq!(
+            Frequency::Weekly.last_day(date("2021-11-21")),
+            date("2021-11-21")
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! Scaling prices, e.g. of listings quoted in pence instead of pounds.
+
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+/// Currencies that some exchanges quote prices in, although they are only a fraction of
+/// the actual currency, together with that currency and how many of them make up one unit
+/// of it.
+const MINOR_UNITS: &[(&str, &str, Decimal)] = &[
+    ("GBX", "GBP", Decimal::ONE_HUNDRED),
+    ("GBp", "GBP", Decimal::ONE_HUNDRED),
+    ("ZAc", "ZAR", Decimal::ONE_HUNDRED),
+    ("ZAC", "ZAR", Decimal::ONE_HUNDRED),
+    ("ILA", "ILS", Decimal::ONE_HUNDRED),
+    ("ILa", "ILS", Decimal::ONE_HUNDRED),
+];
+
+/// How the prices returned by a provider are scaled before they are output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scale {
+    /// Every price is multiplied by this factor
+    Factor(Decimal),
+    /// Prices quoted in a minor currency unit, e.g. pence, are converted into the major
+    /// unit, e.g. pounds. This needs a search request to find out the currency of a listing.
+    Auto,
+}
+
+impl Default for Scale {
+    fn default() -> Self {
+        Self::Factor(Decimal::ONE)
+    }
+}
+
+impl FromStr for Scale {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text == "auto" {
+            return Ok(Self::Auto);
+        }
+        match text.parse::<Decimal>() {
+            Ok(factor) if factor.is_sign_positive() && !factor.is_zero() => {
+                Ok(Self::Factor(factor))
+            }
+            _ => Err(format!(
+                "invalid scale `{text}`, expected a positive number or `auto`"
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Scale {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Number(f64),
+            Name(String),
+        }
+
+        match Value::deserialize(deserializer)? {
+            Value::Number(factor) => factor.to_string().parse(),
+            Value::Name(name) => name.parse(),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Returns the major currency and how many units of `currency` make up one unit of it if
+/// `currency` is a minor currency unit like `GBX` (pence).
+#[must_use]
+pub fn minor_unit(currency: &str) -> Option<(&'static str, Decimal)> {
+    MINOR_UNITS
+        .iter()
+        .find(|(minor, _, _)| *minor == currency)
+        .map(|&(_, major, subunits)| (major, subunits))
+}
@@ -0,0 +1,104 @@
+//! Narrowing down the listings found by a symbol search.
+
+use crate::SymbolMatch;
+
+/// Which listings of a symbol search are kept and which of them come first.
+///
+/// All comparisons ignore ASCII case.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    /// Only keep listings in this region, e.g. `XETRA`
+    pub region: Option<String>,
+    /// Only keep listings of this type, e.g. `ETF`
+    pub asset_type: Option<String>,
+    /// Only keep listings traded in the currency with this ISO code
+    pub currency: Option<String>,
+    /// Regions whose listings come first, the most preferred one first
+    pub preferred_regions: Vec<String>,
+}
+
+impl SearchFilter {
+    /// Returns whether `listing` is kept.
+    #[must_use]
+    pub fn matches(&self, listing: &SymbolMatch) -> bool {
+        let allows = |wanted: &Option<String>, value: &str| {
+            wanted
+                .as_deref()
+                .is_none_or(|wanted| wanted.eq_ignore_ascii_case(value))
+        };
+        allows(&self.region, &listing.region)
+            && allows(&self.asset_type, &listing.asset_type)
+            && allows(&self.currency, &listing.currency)
+    }
+
+    /// Removes the listings that aren't kept and moves the ones in preferred regions to the
+    /// front. Apart from that, the order of `listings` is kept.
+    #[must_use]
+    pub fn apply(&self, mut listings: Vec<SymbolMatch>) -> Vec<SymbolMatch> {
+        listings.retain(|listing| self.matches(listing));
+        listings.sort_by_key(|listing| {
+            self.preferred_regions
+                .iter()
+                .position(|region| region.eq_ignore_ascii_case(&listing.region))
+                .unwrap_or(self.preferred_regions.len())
+        });
+        listings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(symbol: &str, region: &str, currency: &str) -> SymbolMatch {
+        SymbolMatch {
+            symbol: symbol.to_string(),
+            name: "Xtrackers MSCI World".to_string(),
+            asset_type: "ETF".to_string(),
+            region: region.to_string(),
+            currency: currency.to_string(),
+            market_open: "08:00".to_string(),
+            market_close: "20:00".to_string(),
+            timezone: "UTC+01".to_string(),
+            match_score: 0.8,
+        }
+    }
+
+    fn symbols(listings: &[SymbolMatch]) -> Vec<&str> {
+        listings
+            .iter()
+            .map(|listing| listing.symbol.as_str())
+            .collect()
+    }
+
+    fn listings() -> Vec<SymbolMatch> {
+        vec![
+            listing("XDWD.FRK", "Frankfurt", "EUR"),
+            listing("XDWD.LON", "United Kingdom", "USD"),
+            listing("XDWD.DEX", "XETRA", "EUR"),
+            listing("XDWD.TRT", "Toronto", "CAD"),
+        ]
+    }
+
+    #[test]
+    fn preferred_regions_come_first_in_order_of_preference() {
+        let filter = SearchFilter {
+            preferred_regions: vec!["xetra".to_string(), "United Kingdom".to_string()],
+            ..SearchFilter::default()
+        };
+        assert_eq!(
+            symbols(&filter.apply(listings())),
+            ["XDWD.DEX", "XDWD.LON", "XDWD.FRK", "XDWD.TRT"]
+        );
+    }
+
+    #[test]
+    fn filters_are_applied_before_ranking() {
+        let filter = SearchFilter {
+            currency: Some("eur".to_string()),
+            preferred_regions: vec!["XETRA".to_string()],
+            ..SearchFilter::default()
+        };
+        assert_eq!(symbols(&filter.apply(listings())), ["XDWD.DEX", "XDWD.FRK"]);
+    }
+}